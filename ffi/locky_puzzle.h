@@ -0,0 +1,201 @@
+#ifndef LOCKY_PUZZLE_H
+#define LOCKY_PUZZLE_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdint.h>
+
+/**
+ * The call succeeded.
+ */
+#define LOCKY_OK 0
+
+/**
+ * A pointer argument was null.
+ */
+#define LOCKY_ERR_NULL 1
+
+/**
+ * A string argument could not be parsed.
+ */
+#define LOCKY_ERR_PARSE 2
+
+/**
+ * An algorithm turned a locked face.
+ */
+#define LOCKY_ERR_LOCKED 3
+
+/**
+ * A solve did not finish within its timeout.
+ */
+#define LOCKY_ERR_TIMEOUT 4
+
+/**
+ * A solver failed to solve the state.
+ */
+#define LOCKY_ERR_SOLVE 5
+
+/**
+ * A solver, along with the tables it uses.
+ */
+typedef struct LockySolver LockySolver;
+
+/**
+ * A puzzle state.
+ */
+typedef struct LockyState LockyState;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Get a description of the last error on this thread, or null if there has
+ * been none.
+ */
+char *locky_last_error(void);
+
+/**
+ * Free a string returned by this interface.
+ *
+ * # Safety
+ *
+ * The string must have come from this interface and must not be used
+ * afterwards.
+ */
+void locky_string_free(char *s);
+
+/**
+ * Create a solved state.
+ */
+struct LockyState *locky_state_new(void);
+
+/**
+ * Parse a state as facelets or in the format it is printed in.
+ *
+ * Returns null if the state is invalid.
+ *
+ * # Safety
+ *
+ * The string must be null or null-terminated.
+ */
+struct LockyState *locky_state_parse(const char *s);
+
+/**
+ * Copy a state.
+ *
+ * Returns null if the state is null.
+ *
+ * # Safety
+ *
+ * The state must be null or come from this interface.
+ */
+struct LockyState *locky_state_clone(const struct LockyState *state);
+
+/**
+ * Free a state.
+ *
+ * # Safety
+ *
+ * The state must have come from this interface and must not be used
+ * afterwards.
+ */
+void locky_state_free(struct LockyState *state);
+
+/**
+ * Encode a state as a facelet string.
+ *
+ * Returns null if the state is null.
+ *
+ * # Safety
+ *
+ * The state must be null or come from this interface.
+ */
+char *locky_state_to_string(const struct LockyState *state);
+
+/**
+ * Check if a state is valid, i.e. if locky_state_parse() would accept it.
+ *
+ * # Safety
+ *
+ * The string must be null or null-terminated.
+ */
+int locky_validate(const char *s);
+
+/**
+ * Apply an algorithm such as "R U2 F'" to a state.
+ *
+ * If any move turns a locked face, the state is left unchanged and
+ * LOCKY_ERR_LOCKED is returned.
+ *
+ * # Safety
+ *
+ * The state must be null or come from this interface, and the algorithm
+ * must be null or null-terminated.
+ */
+int locky_state_apply(struct LockyState *state, const char *algo);
+
+/**
+ * Check if a face, given by its letter (e.g. 'U'), is locked.
+ *
+ * Returns 1 if it is locked, 0 if it is not, and -1 for an unknown face or
+ * a null state.
+ *
+ * # Safety
+ *
+ * The state must be null or come from this interface.
+ */
+int locky_state_is_locked(const struct LockyState *state, char face);
+
+/**
+ * Check if a state is solved, returning 1 or 0, or -1 for a null state.
+ *
+ * # Safety
+ *
+ * The state must be null or come from this interface.
+ */
+int locky_state_is_solved(const struct LockyState *state);
+
+/**
+ * Create an optimal solver with heuristics of the given depth.
+ *
+ * Depths above 7 take a long time and a lot of memory to generate.
+ */
+struct LockySolver *locky_solver_new_optimal(uint8_t depth);
+
+/**
+ * Create a multi-step solver with the default pipeline.
+ */
+struct LockySolver *locky_solver_new_multi_step(void);
+
+/**
+ * Free a solver.
+ *
+ * # Safety
+ *
+ * The solver must have come from this interface and must not be used
+ * afterwards.
+ */
+void locky_solver_free(struct LockySolver *solver);
+
+/**
+ * Solve a state, storing the solution in *solution on success.
+ *
+ * The search is cancelled at the timeout, so nothing keeps running after
+ * this returns.
+ *
+ * # Safety
+ *
+ * The solver and state must be null or come from this interface, and the
+ * solution pointer must be null or point to writable memory.
+ */
+int locky_solve(const struct LockySolver *solver,
+                const struct LockyState *state,
+                uint64_t timeout_ms,
+                char **solution);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* LOCKY_PUZZLE_H */
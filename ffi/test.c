@@ -0,0 +1,143 @@
+/*
+ * Exercises the C interface of the locky puzzle library.
+ *
+ * Build and run it with `make -C ffi test` after `cargo build --release`.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "locky_puzzle.h"
+
+static int failures = 0;
+
+#define CHECK(cond) do { \
+    if (!(cond)) { \
+        fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+        failures++; \
+    } \
+} while (0)
+
+/* Check that the last error on this thread contains a substring. */
+static void check_error(const char *substring) {
+    char *error = locky_last_error();
+    CHECK(error != NULL && strstr(error, substring) != NULL);
+    locky_string_free(error);
+}
+
+/* Count the moves in a space-separated algorithm. */
+static int count_moves(const char *algo) {
+    int count = 0;
+    for (const char *ch = algo; *ch; ch++) {
+        if (*ch != ' ' && (ch == algo || ch[-1] == ' ')) {
+            count++;
+        }
+    }
+    return count;
+}
+
+static void test_states(void) {
+    LockyState *state = locky_state_new();
+    CHECK(locky_state_is_solved(state) == 1);
+    CHECK(locky_state_apply(state, "F U") == LOCKY_OK);
+    CHECK(locky_state_is_solved(state) == 0);
+    CHECK(locky_state_is_locked(state, 'R') == 1);
+    CHECK(locky_state_is_locked(state, 'U') == 0);
+    CHECK(locky_state_is_locked(state, 'X') == -1);
+
+    /* Illegal moves and bad algorithms leave the state unchanged. */
+    char *before = locky_state_to_string(state);
+    CHECK(locky_state_apply(state, "U' R") == LOCKY_ERR_LOCKED);
+    check_error("locked R face");
+    CHECK(locky_state_apply(state, "U3") == LOCKY_ERR_PARSE);
+    CHECK(locky_state_apply(state, NULL) == LOCKY_ERR_NULL);
+    char *after = locky_state_to_string(state);
+    CHECK(strcmp(before, after) == 0);
+    locky_string_free(after);
+
+    /* States round-trip through facelet strings. */
+    CHECK(locky_validate(before) == LOCKY_OK);
+    LockyState *parsed = locky_state_parse(before);
+    CHECK(parsed != NULL);
+    LockyState *copy = locky_state_clone(parsed);
+    CHECK(locky_state_apply(copy, "U' F'") == LOCKY_OK);
+    CHECK(locky_state_is_solved(copy) == 1);
+    locky_state_free(copy);
+    locky_state_free(parsed);
+    locky_string_free(before);
+
+    CHECK(locky_validate("UU^UUUUUU^U") == LOCKY_ERR_PARSE);
+    check_error("expected 54 stickers");
+    CHECK(locky_state_parse("not a state") == NULL);
+
+    /* Null states are rejected. */
+    CHECK(locky_state_is_solved(NULL) == -1);
+    CHECK(locky_state_is_locked(NULL, 'R') == -1);
+    CHECK(locky_state_clone(NULL) == NULL);
+    CHECK(locky_state_to_string(NULL) == NULL);
+    CHECK(locky_state_apply(NULL, "R") == LOCKY_ERR_NULL);
+    check_error("null state");
+    locky_state_free(state);
+}
+
+static void test_optimal(void) {
+    LockySolver *solver = locky_solver_new_optimal(5);
+    LockyState *state = locky_state_new();
+    CHECK(locky_state_apply(state, "B' U2 D2 L B U") == LOCKY_OK);
+
+    char *solution = NULL;
+    CHECK(locky_solve(solver, state, 10000, &solution) == LOCKY_OK);
+    CHECK(solution != NULL && count_moves(solution) == 6);
+    CHECK(locky_state_apply(state, solution) == LOCKY_OK);
+    CHECK(locky_state_is_solved(state) == 1);
+    locky_string_free(solution);
+
+    /* A long scramble cannot be solved optimally in a millisecond. */
+    CHECK(locky_state_apply(state, "R' F' L' B R B2 L2 D2 B' L2 B' U' F' D2 F' B' R B' L2 D2 "
+                                   "B2 D' B' D R'") == LOCKY_OK);
+    solution = NULL;
+    CHECK(locky_solve(solver, state, 1, &solution) == LOCKY_ERR_TIMEOUT);
+    CHECK(solution == NULL);
+    check_error("no solution within 1 ms");
+    CHECK(locky_solve(solver, state, 1, NULL) == LOCKY_ERR_NULL);
+
+    locky_state_free(state);
+    locky_solver_free(solver);
+}
+
+static void test_multi_step(void) {
+    LockySolver *solver = locky_solver_new_multi_step();
+    LockyState *state = locky_state_new();
+    CHECK(locky_state_apply(state, "D' L2 D L' D2 F2 L2 F' L D'") == LOCKY_OK);
+
+    char *solution = NULL;
+    CHECK(locky_solve(solver, state, 60000, &solution) == LOCKY_OK);
+    CHECK(solution != NULL);
+    CHECK(locky_state_apply(state, solution) == LOCKY_OK);
+    CHECK(locky_state_is_solved(state) == 1);
+    locky_string_free(solution);
+
+    /* A timed-out solve is cancelled before the solver is freed. */
+    CHECK(locky_state_apply(state, "R' F' L' B R B2 L2 D2 B' L2 B' U' F' D2 F' B' R B' L2 D2 "
+                                   "B2 D' B' D R'") == LOCKY_OK);
+    solution = NULL;
+    CHECK(locky_solve(solver, state, 1, &solution) == LOCKY_ERR_TIMEOUT);
+    CHECK(solution == NULL);
+    CHECK(locky_solve(NULL, state, 1, &solution) == LOCKY_ERR_NULL);
+
+    locky_state_free(state);
+    locky_solver_free(solver);
+}
+
+int main(void) {
+    test_states();
+    test_optimal();
+    test_multi_step();
+    if (failures) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    printf("All checks passed\n");
+    return 0;
+}
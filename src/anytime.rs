@@ -0,0 +1,120 @@
+//! Solving the puzzle with solutions that improve over time.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use super::heuristic::{CancelHeuristic, Heuristic};
+use super::moves::{Algo, Metric, MoveSet};
+use super::multi_step::MultiStep;
+use super::proj::{LockProj, Proj};
+use super::solve::solve;
+use super::state::State;
+
+/// A solver which finds a solution quickly and then keeps looking for
+/// shorter ones.
+///
+/// The solver first runs a MultiStep pipeline, then tries beam searches of
+/// increasing widths over the same pipeline, and finally runs an optimal
+/// search.
+pub struct Anytime {
+    pub multi: MultiStep,
+    pub optimal: Arc<Heuristic>,
+    pub beam_widths: Vec<usize>,
+    pub beam_time_limit: Duration
+}
+
+impl Anytime {
+    /// Create an anytime solver from a pipeline and a heuristic for the
+    /// optimal search.
+    pub fn new(multi: MultiStep, optimal: Arc<Heuristic>) -> Anytime {
+        Anytime{
+            multi: multi,
+            optimal: optimal,
+            beam_widths: vec![2, 4, 8],
+            beam_time_limit: Duration::from_secs(10)
+        }
+    }
+
+    /// Generate an anytime solver with reasonable default settings.
+    ///
+    /// The optimal search uses the `CornerProj` table of the default
+    /// pipeline.
+    pub fn generate_default() -> Anytime {
+        let (multi, corner) = MultiStep::generate_default_with_corners();
+        Anytime::new(multi, corner)
+    }
+
+    /// Search for shorter and shorter solutions.
+    ///
+    /// Every solution that is strictly shorter than the previous ones is
+    /// passed to found.
+    /// The search ends once an optimal solution has been found, or soon after
+    /// cancel is set, which interrupts whichever search is running.
+    pub fn solve<F: FnMut(&Algo)>(&self, s: &State, cancel: &AtomicBool, mut found: F) {
+        let mut best: Option<Algo> = None;
+        {
+            let mut improve = |solution: Algo| {
+                if best.as_ref().map(|b| solution.0.len() < b.0.len()).unwrap_or(true) {
+                    found(&solution);
+                    best = Some(solution);
+                }
+            };
+            if let Ok((solution, _)) = self.multi.solve_cancel(s, cancel, None) {
+                improve(solution);
+            }
+            for &width in &self.beam_widths {
+                if cancel.load(Ordering::SeqCst) {
+                    return;
+                }
+                let beam = self.multi.solve_beam(s, width, self.beam_time_limit, cancel);
+                if let Ok((solution, _)) = beam {
+                    improve(solution);
+                }
+            }
+        }
+
+        let heuristic = CancelHeuristic::new(&*self.optimal, cancel);
+        let min_depth = self.optimal.lower_bound(s, LockProj::project(s));
+        let max_depth = best.as_ref().map(|b| b.0.len() as u8).unwrap_or(255);
+        for depth in min_depth..max_depth {
+            if let Some(solution) = solve(s, &heuristic, MoveSet::all(), Metric::FaceTurn, depth) {
+                found(&solution);
+                return;
+            } else if cancel.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heuristic::NopHeuristic;
+    use proj::CornerProj;
+    use step::{ProjStep, SolveStep};
+
+    /// Test that solutions get shorter until they are optimal.
+    #[test]
+    fn improving_solutions() {
+        let nop: Arc<Heuristic> = Arc::new(NopHeuristic());
+        let multi = MultiStep::new(vec![
+            Box::new(ProjStep::<CornerProj>::new("CornerProj", nop.clone(), 5)),
+            Box::new(SolveStep::new(nop.clone(), 5))
+        ]);
+        let anytime = Anytime::new(multi, nop);
+        let algo: Algo = "B D2 B' U2 L2".parse().unwrap();
+        let mut solutions = Vec::new();
+        anytime.solve(&algo.state(), &AtomicBool::new(false), |s| solutions.push(s.clone()));
+        assert!(solutions.len() > 0);
+        for pair in solutions.windows(2) {
+            assert!(pair[1].0.len() < pair[0].0.len());
+        }
+        assert_eq!(solutions.last().unwrap().0.len(), 5);
+
+        let mut cancelled = Vec::new();
+        anytime.solve(&algo.state(), &AtomicBool::new(true), |s| cancelled.push(s.clone()));
+        assert!(cancelled.is_empty());
+    }
+}
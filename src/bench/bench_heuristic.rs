@@ -4,8 +4,8 @@ extern crate locky_puzzle;
 
 use std::time::Instant;
 
-use locky_puzzle::{ArrowAxisProj, CoFbProj, CornerFbProj, CornerProj, LockProj, Proj,
-    ProjHeuristic};
+use locky_puzzle::{ArrowAxisProj, CoFbProj, CornerFbProj, CornerProj, LockProj, Metric, MoveSet,
+    Proj, ProjHeuristic};
 
 fn main() {
     for i in 5..8 {
@@ -31,7 +31,7 @@ fn main() {
 
 fn time_heuristic<T: Proj>(label: &str, depth: u8) {
     let start = Instant::now();
-    let size = ProjHeuristic::<T>::generate(depth).table.len();
+    let size = ProjHeuristic::<T>::generate(depth, MoveSet::all(), Metric::FaceTurn).table.len();
     let elapsed = start.elapsed();
     println!("{}({}) took {} ms (size {})", label, depth, elapsed.as_secs() * 1000 +
         ((elapsed.subsec_nanos() / 1000000) as u64), size)
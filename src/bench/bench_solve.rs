@@ -3,7 +3,7 @@
 extern crate locky_puzzle;
 
 use std::time::Instant;
-use locky_puzzle::{Algo, Heuristic, NopHeuristic, solve};
+use locky_puzzle::{Algo, Heuristic, Metric, MoveSet, NopHeuristic, solve};
 
 fn main() {
     time_solve("NopHeuristic", "B D2 B' U2 L2", &NopHeuristic());
@@ -15,7 +15,7 @@ fn time_solve<H: Heuristic + Sync>(label: &str, scramble: &str, heuristic: &H) {
     let state = scramble.state();
 
     let start = Instant::now();
-    solve(&state, heuristic, scramble.0.len() as u8).unwrap();
+    solve(&state, heuristic, MoveSet::all(), Metric::FaceTurn, scramble.0.len() as u8).unwrap();
     let elapsed = start.elapsed();
 
     println!("{}({}) took {} ms", label, scramble, elapsed.as_secs() * 1000 +
@@ -0,0 +1,137 @@
+//! Estimating how hard algorithms are to execute by hand.
+
+use std::cmp::Ordering;
+
+use super::moves::{Algo, Move, Turns};
+use super::state::Face;
+
+/// A model of how hard it is to perform moves on the physical puzzle.
+///
+/// The cost of an algorithm is the sum of the costs of its moves, where the
+/// cost of a move may depend on the move before it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CostModel {
+    /// The cost of a quarter turn.
+    pub quarter_turn: f64,
+
+    /// The cost of a half turn.
+    pub half_turn: f64,
+
+    /// An extra cost for turning each face, in the order U, D, F, B, R, L.
+    pub face_penalties: [f64; 6],
+
+    /// The cost of changing grips.
+    ///
+    /// The F and B faces are turned with a different grip than the other
+    /// faces, so moving between the two groups of faces requires a regrip.
+    pub regrip: f64,
+
+    /// An extra cost for turning two faces on the same axis in a row.
+    pub same_axis: f64
+}
+
+impl CostModel {
+    /// Get the cost of a move, given the move that was performed before it.
+    pub fn move_cost(&self, prev: Option<Move>, m: Move) -> f64 {
+        let mut cost = match m.turns {
+            Turns::Double => self.half_turn,
+            _ => self.quarter_turn
+        };
+        cost += self.face_penalties[face_index(m.face)];
+        if let Some(prev) = prev {
+            if is_front_back(prev.face) != is_front_back(m.face) {
+                cost += self.regrip;
+            }
+            if axis_index(prev.face) == axis_index(m.face) {
+                cost += self.same_axis;
+            }
+        }
+        cost
+    }
+
+    /// Get the total cost of an algorithm.
+    pub fn algo_cost(&self, algo: &Algo) -> f64 {
+        let mut prev = None;
+        let mut cost = 0.0;
+        for m in &algo.0 {
+            cost += self.move_cost(prev, *m);
+            prev = Some(*m);
+        }
+        cost
+    }
+
+    /// Sort algorithms from easiest to hardest.
+    ///
+    /// Algorithms with the same cost keep their original order.
+    pub fn rank(&self, algos: Vec<Algo>) -> Vec<Algo> {
+        let mut costs: Vec<(f64, Algo)> = algos.into_iter()
+            .map(|a| (self.algo_cost(&a), a))
+            .collect();
+        costs.sort_by(|&(c1, _), &(c2, _)| c1.partial_cmp(&c2).unwrap_or(Ordering::Equal));
+        costs.into_iter().map(|(_, a)| a).collect()
+    }
+}
+
+impl Default for CostModel {
+    /// Create a cost model that favors R and U turns.
+    fn default() -> CostModel {
+        CostModel{
+            quarter_turn: 1.0,
+            half_turn: 1.4,
+            face_penalties: [0.0, 0.6, 0.3, 1.0, 0.0, 0.2],
+            regrip: 1.0,
+            same_axis: 0.3
+        }
+    }
+}
+
+fn face_index(face: Face) -> usize {
+    use Face::*;
+    match face {
+        U => 0,
+        D => 1,
+        F => 2,
+        B => 3,
+        R => 4,
+        L => 5
+    }
+}
+
+fn axis_index(face: Face) -> usize {
+    face_index(face) / 2
+}
+
+fn is_front_back(face: Face) -> bool {
+    face == Face::F || face == Face::B
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test the costs of simple algorithms.
+    #[test]
+    fn algo_costs() {
+        let model = CostModel{
+            quarter_turn: 1.0,
+            half_turn: 2.0,
+            face_penalties: [0.0, 0.5, 0.0, 0.5, 0.0, 0.0],
+            regrip: 3.0,
+            same_axis: 0.25
+        };
+        let algo: Algo = "R U2 D F'".parse().unwrap();
+        assert_eq!(model.algo_cost(&algo), 1.0 + 2.0 + 1.75 + 4.0);
+        assert_eq!(model.algo_cost(&Algo(Vec::new())), 0.0);
+    }
+
+    /// Test ranking algorithms of the same length.
+    #[test]
+    fn rank_algos() {
+        let algos: Vec<Algo> = ["B D", "F R", "R U", "U R'"].iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let ranked = CostModel::default().rank(algos);
+        let actual: Vec<String> = ranked.iter().map(|a| format!("{}", a)).collect();
+        assert_eq!(actual, vec!["R U", "U R'", "F R", "B D"]);
+    }
+}
@@ -0,0 +1,420 @@
+//! A C interface for embedding the puzzle in programs written in other
+//! languages.
+//!
+//! Functions that can fail return one of the LOCKY_* status codes, and a
+//! description of the most recent failure on the calling thread can be read
+//! with locky_last_error().
+//! Strings returned by this interface must be freed with locky_string_free().
+//! Null state and solver pointers are rejected with LOCKY_ERR_NULL.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, channel};
+use std::thread::spawn;
+use std::time::{Duration, Instant};
+
+use super::heuristic::{CancelHeuristic, Heuristic, MaxHeuristic};
+use super::moves::{Algo, Metric, MoveSet};
+use super::multi_step::{MultiStep, MultiStepError, generate_table};
+use super::proj::{ArrowAxisProj, CornerProj, LockProj, Proj};
+use super::solve::solve;
+use super::state::{Face, State};
+
+/// The call succeeded.
+pub const LOCKY_OK: c_int = 0;
+
+/// A pointer argument was null.
+pub const LOCKY_ERR_NULL: c_int = 1;
+
+/// A string argument could not be parsed.
+pub const LOCKY_ERR_PARSE: c_int = 2;
+
+/// An algorithm turned a locked face.
+pub const LOCKY_ERR_LOCKED: c_int = 3;
+
+/// A solve did not finish within its timeout.
+pub const LOCKY_ERR_TIMEOUT: c_int = 4;
+
+/// A solver failed to solve the state.
+pub const LOCKY_ERR_SOLVE: c_int = 5;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// A puzzle state.
+pub struct LockyState(State);
+
+/// A solver, along with the tables it uses.
+pub struct LockySolver(Solver);
+
+enum Solver {
+    Optimal(MaxHeuristic<Arc<Heuristic>>),
+    MultiStep(MultiStep)
+}
+
+/// Record an error for locky_last_error() and return its status code.
+fn fail(status: c_int, message: String) -> c_int {
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+    status
+}
+
+/// Read a C string, recording an error if it is null or not UTF-8.
+unsafe fn read_str<'a>(s: *const c_char) -> Result<&'a str, c_int> {
+    if s.is_null() {
+        return Err(fail(LOCKY_ERR_NULL, "unexpected null string".to_owned()));
+    }
+    CStr::from_ptr(s).to_str()
+        .map_err(|e| fail(LOCKY_ERR_PARSE, format!("invalid UTF-8: {}", e)))
+}
+
+/// Dereference a state or solver pointer, recording an error if it is null.
+unsafe fn read_ptr<'a, T>(p: *const T, name: &str) -> Result<&'a T, c_int> {
+    p.as_ref().ok_or_else(|| fail(LOCKY_ERR_NULL, format!("unexpected null {} pointer", name)))
+}
+
+fn to_c_string(s: String) -> *mut c_char {
+    // Rust strings produced by this crate never contain null bytes.
+    CString::new(s).unwrap().into_raw()
+}
+
+/// Get a description of the last error on this thread, or null if there has
+/// been none.
+#[no_mangle]
+pub extern "C" fn locky_last_error() -> *mut c_char {
+    LAST_ERROR.with(|e| e.borrow().clone().map(to_c_string).unwrap_or(ptr::null_mut()))
+}
+
+/// Free a string returned by this interface.
+///
+/// # Safety
+///
+/// The string must have come from this interface and must not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn locky_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Create a solved state.
+#[no_mangle]
+pub extern "C" fn locky_state_new() -> *mut LockyState {
+    Box::into_raw(Box::new(LockyState(State::default())))
+}
+
+/// Parse a state as facelets or in the format it is printed in.
+///
+/// Returns null if the state is invalid.
+///
+/// # Safety
+///
+/// The string must be null or null-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn locky_state_parse(s: *const c_char) -> *mut LockyState {
+    let s = match read_str(s) {
+        Ok(s) => s,
+        Err(_) => return ptr::null_mut()
+    };
+    match s.parse() {
+        Ok(state) => Box::into_raw(Box::new(LockyState(state))),
+        Err(e) => {
+            fail(LOCKY_ERR_PARSE, format!("parse state: {}", e));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Copy a state.
+///
+/// Returns null if the state is null.
+///
+/// # Safety
+///
+/// The state must be null or come from this interface.
+#[no_mangle]
+pub unsafe extern "C" fn locky_state_clone(state: *const LockyState) -> *mut LockyState {
+    match read_ptr(state, "state") {
+        Ok(state) => Box::into_raw(Box::new(LockyState(state.0.clone()))),
+        Err(_) => ptr::null_mut()
+    }
+}
+
+/// Free a state.
+///
+/// # Safety
+///
+/// The state must have come from this interface and must not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn locky_state_free(state: *mut LockyState) {
+    if !state.is_null() {
+        drop(Box::from_raw(state));
+    }
+}
+
+/// Encode a state as a facelet string.
+///
+/// Returns null if the state is null.
+///
+/// # Safety
+///
+/// The state must be null or come from this interface.
+#[no_mangle]
+pub unsafe extern "C" fn locky_state_to_string(state: *const LockyState) -> *mut c_char {
+    match read_ptr(state, "state") {
+        Ok(state) => to_c_string(state.0.to_facelet_string()),
+        Err(_) => ptr::null_mut()
+    }
+}
+
+/// Check if a state is valid, i.e. if locky_state_parse() would accept it.
+///
+/// # Safety
+///
+/// The string must be null or null-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn locky_validate(s: *const c_char) -> c_int {
+    let s = match read_str(s) {
+        Ok(s) => s,
+        Err(status) => return status
+    };
+    match s.parse::<State>() {
+        Ok(_) => LOCKY_OK,
+        Err(e) => fail(LOCKY_ERR_PARSE, format!("parse state: {}", e))
+    }
+}
+
+/// Apply an algorithm such as "R U2 F'" to a state.
+///
+/// If any move turns a locked face, the state is left unchanged and
+/// LOCKY_ERR_LOCKED is returned.
+///
+/// # Safety
+///
+/// The state must be null or come from this interface, and the algorithm
+/// must be null or null-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn locky_state_apply(
+    state: *mut LockyState,
+    algo: *const c_char
+) -> c_int {
+    let state = match state.as_mut() {
+        Some(state) => state,
+        None => return fail(LOCKY_ERR_NULL, "unexpected null state pointer".to_owned())
+    };
+    let algo: Algo = match read_str(algo).map(|a| a.parse()) {
+        Ok(Ok(algo)) => algo,
+        Ok(Err(e)) => return fail(LOCKY_ERR_PARSE, format!("parse algorithm: {}", e)),
+        Err(status) => return status
+    };
+    match algo.apply_legal(&mut state.0) {
+        Ok(()) => LOCKY_OK,
+        Err(e) => fail(LOCKY_ERR_LOCKED, e.to_string())
+    }
+}
+
+/// Check if a face, given by its letter (e.g. 'U'), is locked.
+///
+/// Returns 1 if it is locked, 0 if it is not, and -1 for an unknown face or
+/// a null state.
+///
+/// # Safety
+///
+/// The state must be null or come from this interface.
+#[no_mangle]
+pub unsafe extern "C" fn locky_state_is_locked(state: *const LockyState, face: c_char) -> c_int {
+    let state = match read_ptr(state, "state") {
+        Ok(state) => state,
+        Err(_) => return -1
+    };
+    match Face::from_char(face as u8 as char) {
+        Some(face) => state.0.is_locked(face) as c_int,
+        None => {
+            fail(LOCKY_ERR_PARSE, format!("unknown face: {}", face as u8 as char));
+            -1
+        }
+    }
+}
+
+/// Check if a state is solved, returning 1 or 0, or -1 for a null state.
+///
+/// # Safety
+///
+/// The state must be null or come from this interface.
+#[no_mangle]
+pub unsafe extern "C" fn locky_state_is_solved(state: *const LockyState) -> c_int {
+    match read_ptr(state, "state") {
+        Ok(state) => state.0.is_solved() as c_int,
+        Err(_) => -1
+    }
+}
+
+/// Create an optimal solver with heuristics of the given depth.
+///
+/// Depths above 7 take a long time and a lot of memory to generate.
+#[no_mangle]
+pub extern "C" fn locky_solver_new_optimal(depth: u8) -> *mut LockySolver {
+    let corner_rx = generate_table::<CornerProj>(depth);
+    let arrow_rx = generate_table::<ArrowAxisProj>(depth);
+    let heuristic = MaxHeuristic(vec![corner_rx.recv().unwrap(), arrow_rx.recv().unwrap()]);
+    Box::into_raw(Box::new(LockySolver(Solver::Optimal(heuristic))))
+}
+
+/// Create a multi-step solver with the default pipeline.
+#[no_mangle]
+pub extern "C" fn locky_solver_new_multi_step() -> *mut LockySolver {
+    let multi = MultiStep::generate_default();
+    Box::into_raw(Box::new(LockySolver(Solver::MultiStep(multi))))
+}
+
+/// Free a solver.
+///
+/// # Safety
+///
+/// The solver must have come from this interface and must not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn locky_solver_free(solver: *mut LockySolver) {
+    if !solver.is_null() {
+        drop(Box::from_raw(solver));
+    }
+}
+
+/// Solve a state, storing the solution in *solution on success.
+///
+/// The search is cancelled at the timeout, so nothing keeps running after
+/// this returns.
+///
+/// # Safety
+///
+/// The solver and state must be null or come from this interface, and the
+/// solution pointer must be null or point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn locky_solve(
+    solver: *const LockySolver,
+    state: *const LockyState,
+    timeout_ms: u64,
+    solution: *mut *mut c_char
+) -> c_int {
+    let (solver, state) = match (read_ptr(solver, "solver"), read_ptr(state, "state")) {
+        (Ok(solver), Ok(state)) => (solver, state),
+        (Err(status), _) | (_, Err(status)) => return status
+    };
+    if solution.is_null() {
+        return fail(LOCKY_ERR_NULL, "unexpected null solution pointer".to_owned());
+    }
+    let timeout = Duration::from_millis(timeout_ms);
+    let result = match solver.0 {
+        Solver::Optimal(ref heuristic) => solve_optimal(heuristic, &state.0, timeout),
+        Solver::MultiStep(ref multi) => solve_multi_step(multi, &state.0, timeout)
+    };
+    match result {
+        Ok(algo) => {
+            *solution = to_c_string(algo.to_string());
+            LOCKY_OK
+        },
+        Err((status, message)) => fail(status, message)
+    }
+}
+
+fn solve_optimal(
+    heuristic: &MaxHeuristic<Arc<Heuristic>>,
+    state: &State,
+    timeout: Duration
+) -> Result<Algo, (c_int, String)> {
+    // Cancel the search once the timeout passes, unless it finishes first.
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (done_tx, done_rx) = channel::<()>();
+    let timer_cancel = cancel.clone();
+    spawn(move || {
+        if let Err(RecvTimeoutError::Timeout) = done_rx.recv_timeout(timeout) {
+            timer_cancel.store(true, Ordering::SeqCst);
+        }
+    });
+
+    let cancel_heuristic = CancelHeuristic::new(heuristic, &cancel);
+    let min_depth = heuristic.lower_bound(state, LockProj::project(state));
+    let mut result = None;
+    for depth in min_depth..255 {
+        result = solve(state, &cancel_heuristic, MoveSet::all(), Metric::FaceTurn, depth);
+        if result.is_some() || cancel.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+    drop(done_tx);
+    result.ok_or_else(|| {
+        (LOCKY_ERR_TIMEOUT, format!("no solution within {} ms", timeout_ms(timeout)))
+    })
+}
+
+fn solve_multi_step(
+    multi: &MultiStep,
+    state: &State,
+    timeout: Duration
+) -> Result<Algo, (c_int, String)> {
+    let deadline = Instant::now() + timeout;
+    match multi.solve_cancel(state, &AtomicBool::new(false), Some(deadline)) {
+        Ok((solution, _)) => Ok(solution),
+        Err(MultiStepError::Cancelled{..}) => {
+            Err((LOCKY_ERR_TIMEOUT, format!("no solution within {} ms", timeout_ms(timeout))))
+        },
+        Err(e) => Err((LOCKY_ERR_SOLVE, e.to_string()))
+    }
+}
+
+fn timeout_ms(timeout: Duration) -> u64 {
+    timeout.as_secs() * 1000 + timeout.subsec_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test applying moves and solving through the C interface.
+    #[test]
+    fn c_interface() {
+        unsafe {
+            let state = locky_state_new();
+            let algo = CString::new("F U").unwrap();
+            assert_eq!(locky_state_apply(state, algo.as_ptr()), LOCKY_OK);
+            assert_eq!(locky_state_is_locked(state, 'R' as c_char), 1);
+            assert_eq!(locky_state_is_locked(state, 'X' as c_char), -1);
+
+            // An illegal move leaves the state unchanged.
+            let before = (*state).0.clone();
+            let algo = CString::new("U' R").unwrap();
+            assert_eq!(locky_state_apply(state, algo.as_ptr()), LOCKY_ERR_LOCKED);
+            assert_eq!((*state).0, before);
+            let error = locky_last_error();
+            assert!(CStr::from_ptr(error).to_str().unwrap().contains("locked R face"));
+            locky_string_free(error);
+
+            let facelets = locky_state_to_string(state);
+            assert_eq!(locky_validate(facelets), LOCKY_OK);
+            let parsed = locky_state_parse(facelets);
+            assert_eq!((*parsed).0, before);
+            locky_string_free(facelets);
+            locky_state_free(parsed);
+
+            let solver = locky_solver_new_optimal(3);
+            let mut solution = ptr::null_mut();
+            assert_eq!(locky_solve(solver, state, 10000, &mut solution), LOCKY_OK);
+            assert_eq!(CStr::from_ptr(solution).to_str().unwrap(), "U' F'");
+            locky_string_free(solution);
+            locky_solver_free(solver);
+
+            // Null pointers are rejected rather than dereferenced.
+            assert_eq!(locky_state_apply(ptr::null_mut(), algo.as_ptr()), LOCKY_ERR_NULL);
+            assert_eq!(locky_state_is_solved(ptr::null()), -1);
+            assert!(locky_state_clone(ptr::null()).is_null());
+            assert_eq!(locky_solve(ptr::null(), state, 1, &mut solution), LOCKY_ERR_NULL);
+            locky_state_free(state);
+        }
+    }
+}
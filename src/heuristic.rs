@@ -1,9 +1,14 @@
 //! Abstractions for search heuristics.
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::collections::hash_map::Entry;
+use std::mem::take;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 
 use super::move_gen::MoveGen;
+use super::moves::{Metric, MoveSet};
 use super::proj::{LockProj, Proj};
 use super::state::State;
 
@@ -18,6 +23,12 @@ impl Heuristic for Box<Heuristic> {
     }
 }
 
+impl Heuristic for Arc<Heuristic> {
+    fn lower_bound(&self, s: &State, l: LockProj) -> u8 {
+        self.as_ref().lower_bound(s, l)
+    }
+}
+
 impl<'a> Heuristic for &'a Heuristic {
     fn lower_bound(&self, s: &State, l: LockProj) -> u8 {
         (*self).lower_bound(s, l)
@@ -46,6 +57,47 @@ impl Heuristic for NopHeuristic {
     }
 }
 
+/// A heuristic that prunes every branch once a search has been cancelled.
+pub struct CancelHeuristic<'a> {
+    inner: &'a Heuristic,
+    cancel: &'a AtomicBool,
+    deadline: Option<Instant>
+}
+
+impl<'a> CancelHeuristic<'a> {
+    /// Wrap a heuristic so that searches using it end soon after cancel is
+    /// set.
+    pub fn new(inner: &'a Heuristic, cancel: &'a AtomicBool) -> CancelHeuristic<'a> {
+        CancelHeuristic::with_deadline(inner, cancel, None)
+    }
+
+    /// Wrap a heuristic so that searches using it end soon after cancel is
+    /// set or the deadline has passed.
+    pub fn with_deadline(
+        inner: &'a Heuristic,
+        cancel: &'a AtomicBool,
+        deadline: Option<Instant>
+    ) -> CancelHeuristic<'a> {
+        CancelHeuristic{inner: inner, cancel: cancel, deadline: deadline}
+    }
+
+    /// Check if searches using this heuristic are being cut off.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+            || self.deadline.map(|d| Instant::now() >= d).unwrap_or(false)
+    }
+}
+
+impl<'a> Heuristic for CancelHeuristic<'a> {
+    fn lower_bound(&self, s: &State, l: LockProj) -> u8 {
+        if self.is_cancelled() {
+            255
+        } else {
+            self.inner.lower_bound(s, l)
+        }
+    }
+}
+
 /// A heuristic that uses a lookup table of projections.
 pub struct ProjHeuristic<T: Proj> {
     pub table: HashMap<T, u8>,
@@ -54,29 +106,49 @@ pub struct ProjHeuristic<T: Proj> {
 
 impl<T: Proj> ProjHeuristic<T> {
     /// Uses a simple search algorithm to build a heuristic table.
-    pub fn generate(depth: u8) -> Self {
+    ///
+    /// Only moves from the given move set and their inverses are explored,
+    /// so the resulting table is a lower bound for searches restricted to
+    /// that move set.
+    /// The table is built forward from the solved state, so a set which is
+    /// not closed under inverse (e.g. only "U'") would otherwise give the
+    /// distances of the inverse moves rather than a lower bound.
+    /// The depth and the distances in the table are measured in the given
+    /// metric.
+    pub fn generate(depth: u8, moves: MoveSet, metric: Metric) -> Self {
         let mut table = HashMap::new();
-        let mut states = VecDeque::new();
-        states.push_back((MoveGen::new(), State::default()));
         table.insert(Proj::project(&State::default()), 0);
-        for i in 0..depth {
-            let pop_size = states.len();
-            if pop_size == 0 {
-                break;
-            }
-            for _ in 0..pop_size {
-                let (moves, state) = states.pop_front().unwrap();
+
+        // Moves may cost more than one, so we keep a separate queue for
+        // every distance rather than a single FIFO queue.
+        let mut levels = vec![Vec::new(); depth as usize + 1];
+        levels[0].push((MoveGen::restricted(moves.with_inverses()), State::default()));
+        for i in 0..(depth as usize) {
+            for (moves, state) in take(&mut levels[i]) {
+                if table[&T::project(&state)] < i as u8 {
+                    // A shorter path to this projection was found later on.
+                    continue;
+                }
                 for (new_moves, m) in moves {
-                    if state.is_locked(m.face) {
+                    let new_dist = i + metric.cost(m) as usize;
+                    if state.is_locked(m.face) || new_dist > depth as usize {
                         continue;
                     }
                     let mut new_state = state.clone();
                     m.apply(&mut new_state);
                     let proj = Proj::project(&new_state);
-                    if let Entry::Vacant(v) = table.entry(proj) {
-                        v.insert(i + 1);
-                        states.push_back((new_moves.clone(), new_state));
+                    match table.entry(proj) {
+                        Entry::Vacant(v) => {
+                            v.insert(new_dist as u8);
+                        },
+                        Entry::Occupied(mut o) => {
+                            if *o.get() as usize <= new_dist {
+                                continue;
+                            }
+                            o.insert(new_dist as u8);
+                        }
                     }
+                    levels[new_dist].push((new_moves.clone(), new_state));
                 }
             }
         }
@@ -96,39 +168,59 @@ impl<T: Proj> Heuristic for ProjHeuristic<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use state::Face;
     use proj::{ArrowAxisProj, CoFbProj, CoRlProj, CoUdProj, CornerFbProj, CornerProj, CornerRlProj,
         CornerUdProj};
+    use moves::{Algo, Metric, MoveSet};
 
     #[test]
     fn generate_heuristic() {
-        let corners_1: ProjHeuristic<CornerProj> = ProjHeuristic::generate(1);
+        let all = MoveSet::all();
+        let ftm = Metric::FaceTurn;
+
+        let corners_1: ProjHeuristic<CornerProj> = ProjHeuristic::generate(1, all, ftm);
         assert_eq!(corners_1.table.len(), 19);
 
-        let corners_2: ProjHeuristic<CornerProj> = ProjHeuristic::generate(2);
+        let corners_2: ProjHeuristic<CornerProj> = ProjHeuristic::generate(2, all, ftm);
         assert_eq!(corners_2.table.len(), 190);
 
-        let corners_5: ProjHeuristic<CornerProj> = ProjHeuristic::generate(5);
+        let corners_5: ProjHeuristic<CornerProj> = ProjHeuristic::generate(5, all, ftm);
         assert_eq!(corners_5.table.len(), 77362);
 
-        let arrows_5: ProjHeuristic<ArrowAxisProj> = ProjHeuristic::generate(5);
+        let arrows_5: ProjHeuristic<ArrowAxisProj> = ProjHeuristic::generate(5, all, ftm);
         assert_eq!(arrows_5.table.len(), 66756);
 
-        let co_5_ud: ProjHeuristic<CoUdProj> = ProjHeuristic::generate(5);
+        let co_5_ud: ProjHeuristic<CoUdProj> = ProjHeuristic::generate(5, all, ftm);
         assert_eq!(co_5_ud.table.len(), 71055);
 
-        let co_5_fb: ProjHeuristic<CoFbProj> = ProjHeuristic::generate(5);
+        let co_5_fb: ProjHeuristic<CoFbProj> = ProjHeuristic::generate(5, all, ftm);
         assert_eq!(co_5_fb.table.len(), 71055);
 
-        let co_5_rl: ProjHeuristic<CoRlProj> = ProjHeuristic::generate(5);
+        let co_5_rl: ProjHeuristic<CoRlProj> = ProjHeuristic::generate(5, all, ftm);
         assert_eq!(co_5_rl.table.len(), 71055);
 
-        let corner_5_ud: ProjHeuristic<CornerUdProj> = ProjHeuristic::generate(5);
+        let corner_5_ud: ProjHeuristic<CornerUdProj> = ProjHeuristic::generate(5, all, ftm);
         assert_eq!(corner_5_ud.table.len(), 71074);
 
-        let corner_5_fb: ProjHeuristic<CornerFbProj> = ProjHeuristic::generate(5);
+        let corner_5_fb: ProjHeuristic<CornerFbProj> = ProjHeuristic::generate(5, all, ftm);
         assert_eq!(corner_5_fb.table.len(), 71074);
 
-        let corner_5_rl: ProjHeuristic<CornerRlProj> = ProjHeuristic::generate(5);
+        let corner_5_rl: ProjHeuristic<CornerRlProj> = ProjHeuristic::generate(5, all, ftm);
         assert_eq!(corner_5_rl.table.len(), 71074);
+
+        let ru = MoveSet::faces(&[Face::R, Face::U]);
+        let ru_corners_5: ProjHeuristic<CornerProj> = ProjHeuristic::generate(5, ru, ftm);
+        assert_eq!(ru_corners_5.table.len(), 217);
+
+        let u_prime: MoveSet = "U'".parse().unwrap();
+        let u_prime_corners: ProjHeuristic<CornerProj> = ProjHeuristic::generate(3, u_prime, ftm);
+        let u = "U".parse::<Algo>().unwrap().state();
+        assert_eq!(u_prime_corners.lower_bound(&u, LockProj::project(&u)), 1);
+
+        let qtm_corners_2: ProjHeuristic<CornerProj> = ProjHeuristic::generate(2, all,
+            Metric::QuarterTurn);
+        assert_eq!(qtm_corners_2.table.len(), 103);
+        let u2 = "U2".parse::<Algo>().unwrap().state();
+        assert_eq!(qtm_corners_2.lower_bound(&u2, LockProj::project(&u2)), 2);
     }
 }
@@ -0,0 +1,77 @@
+//! Converting puzzle types to JSON for machine-readable output.
+
+use super::serde_json::Value;
+
+use super::moves::Algo;
+use super::multi_step::MultiStepError;
+use super::state::State;
+use super::verify::VerifyError;
+
+/// Describe a state by its facelet string and its locked faces.
+pub fn state_to_json(state: &State) -> Value {
+    let locked: Vec<String> = state.locked_faces().iter().map(|f| f.to_string()).collect();
+    json!({
+        "facelets": state.to_facelet_string(),
+        "locked": locked
+    })
+}
+
+/// Describe an algorithm by its moves and its length in moves.
+pub fn algo_to_json(algo: &Algo) -> Value {
+    json!({
+        "moves": algo.to_string(),
+        "length": algo.0.len()
+    })
+}
+
+/// Describe a failed multi-step solve, including the moves that were found
+/// before the failure.
+pub fn error_to_json(e: &MultiStepError) -> Value {
+    let mut res = json!({
+        "message": e.to_string(),
+        "partial": algo_to_json(e.partial()),
+        "state": state_to_json(e.state())
+    });
+    match e {
+        &MultiStepError::StepFailed{index, ref name, depth, ..} => {
+            res["kind"] = json!("step_failed");
+            res["step"] = json!(index + 1);
+            res["name"] = json!(name);
+            res["depth"] = json!(depth);
+        },
+        &MultiStepError::Unsolved{..} => res["kind"] = json!("unsolved"),
+        &MultiStepError::Cancelled{..} => res["kind"] = json!("cancelled")
+    }
+    res
+}
+
+/// Describe an invalid solution, including the state when the check failed.
+pub fn verify_error_to_json(e: &VerifyError) -> Value {
+    let mut res = json!({
+        "message": e.to_string(),
+        "state": state_to_json(e.state())
+    });
+    match e {
+        &VerifyError::IllegalMove{index, m, ..} => {
+            res["kind"] = json!("illegal_move");
+            res["index"] = json!(index + 1);
+            res["move"] = json!(m.to_string());
+        },
+        &VerifyError::Unsolved{..} => res["kind"] = json!("unsolved")
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test describing a state.
+    #[test]
+    fn state_json() {
+        let state = "F".parse::<Algo>().unwrap().state();
+        let actual = state_to_json(&state);
+        assert_eq!(actual["facelets"], json!(state.to_facelet_string()));
+        assert_eq!(actual["locked"], json!(["R", "L"]));
+    }
+}
@@ -1,24 +1,47 @@
 //! An API for the "Locky Puzzle".
 
 extern crate rand;
+#[macro_use]
+extern crate serde_json;
 
 mod state;
 
+mod anytime;
+mod ergonomics;
+pub mod ffi;
 mod heuristic;
+mod json;
 mod move_gen;
 mod moves;
 mod multi_step;
+mod optimize;
 mod proj;
+mod render;
 mod scramble;
 mod solve;
+mod step;
+mod svg;
 mod thread;
+mod two_phase;
+mod verify;
 
-pub use heuristic::{Heuristic, MaxHeuristic, NopHeuristic, ProjHeuristic};
+pub use anytime::Anytime;
+pub use ergonomics::CostModel;
+pub use heuristic::{CancelHeuristic, Heuristic, MaxHeuristic, NopHeuristic, ProjHeuristic};
+pub use json::{algo_to_json, error_to_json, state_to_json, verify_error_to_json};
 pub use move_gen::{MoveGen};
-pub use moves::{ALL_MOVES, Algo, Move, ParseMoveError, Turns};
+pub use moves::{ALL_MOVES, Algo, LockedMoveError, Metric, Move, MoveSet, ParseMoveError, Turns};
 pub use multi_step::{MultiStep, MultiStepError};
+pub use optimize::optimize;
 pub use proj::{ArrowAxisProj, CoFbProj, CoRlProj, CoUdProj, CornerFbProj, CornerProj, CornerRlProj,
-    CornerUdProj, Proj, LockProj};
-pub use scramble::scramble;
-pub use solve::{proj_solve, proj_solve_serial, solve, solve_serial};
-pub use state::{Face, Direction, State, Sticker};
+    CornerUdProj, PairProj, Proj, LockProj};
+pub use render::{TextStyle, render_net};
+pub use scramble::{filtered_scramble, has_min_distance, random_state_scramble, scramble,
+    scramble_with_rng, seeded_rng};
+pub use solve::{proj_solve, proj_solve_all, proj_solve_serial, solve, solve_all, solve_between,
+    solve_serial};
+pub use step::{FallbackStep, ProjStep, SolveStep, Step};
+pub use svg::{SvgView, render_algo_svg, render_svg};
+pub use state::{Face, Direction, ParseStateError, State, Sticker};
+pub use two_phase::{Phase1Proj, TwoPhase};
+pub use verify::{Goal, VerifyError, verify};
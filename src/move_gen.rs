@@ -1,6 +1,6 @@
 //! Generating move sequences for searches.
 
-use super::moves::{ALL_MOVES, Move};
+use super::moves::{ALL_MOVES, Move, MoveSet};
 use super::state::Face;
 
 /// An object representing a certain point in a search tree, and in particular
@@ -8,16 +8,28 @@ use super::state::Face;
 #[derive(Clone)]
 pub struct MoveGen {
     axis: Axis,
-    axis_state: AxisState
+    axis_state: AxisState,
+    moves: MoveSet
 }
 
 impl MoveGen {
     pub fn new() -> MoveGen {
+        MoveGen::restricted(MoveSet::all())
+    }
+
+    /// Create a MoveGen that only produces moves from the given set.
+    pub fn restricted(moves: MoveSet) -> MoveGen {
         MoveGen{
             axis: Axis::UD,
-            axis_state: AxisState::Enabled
+            axis_state: AxisState::Enabled,
+            moves: moves
         }
     }
+
+    /// Get the set of moves that this MoveGen draws from.
+    pub fn move_set(&self) -> MoveSet {
+        self.moves
+    }
 }
 
 impl IntoIterator for MoveGen {
@@ -46,6 +58,9 @@ impl Iterator for MoveGenIter {
             let m = ALL_MOVES[self.idx];
             let (axis, primary) = decompose_face(m.face);
             self.idx += 1;
+            if !self.state.moves.contains(m) {
+                continue;
+            }
             if axis != self.state.axis || self.state.axis_state == AxisState::Enabled {
                 return Some((MoveGen{
                     axis: axis,
@@ -53,12 +68,14 @@ impl Iterator for MoveGenIter {
                         AxisState::HalfDisabled
                     } else {
                         AxisState::Disabled
-                    }
+                    },
+                    moves: self.state.moves
                 }, m));
             } else if !primary && self.state.axis_state == AxisState::HalfDisabled {
                 return Some((MoveGen{
                     axis: axis,
-                    axis_state: AxisState::Disabled
+                    axis_state: AxisState::Disabled,
+                    moves: self.state.moves
                 }, m));
             }
         }
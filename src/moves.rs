@@ -28,7 +28,7 @@ pub const ALL_MOVES: [Move; 18] = [
     Move{face: Face::L, turns: Turns::Counter}
 ];
 
-/// A description of a single move on the cube, in the face-turn metric.
+/// A description of a single move on the cube.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Move {
     pub face: Face,
@@ -98,6 +98,47 @@ impl Algo {
         self.apply(&mut res);
         res
     }
+
+    /// Apply the moves to a state, checking that each one turns an unlocked
+    /// face.
+    ///
+    /// If a move turns a locked face, the state is left unchanged.
+    pub fn apply_legal(&self, s: &mut State) -> Result<(), LockedMoveError> {
+        let mut res = s.clone();
+        for (i, m) in self.0.iter().enumerate() {
+            if res.is_locked(m.face) {
+                return Err(LockedMoveError{index: i, m: *m});
+            }
+            m.apply(&mut res);
+        }
+        *s = res;
+        Ok(())
+    }
+
+    /// Apply the moves to the solved state, checking that each one turns an
+    /// unlocked face.
+    ///
+    /// Scrambles should be checked this way, since scrambles that turn
+    /// locked faces may lead to states that cannot be solved.
+    pub fn legal_state(&self) -> Result<State, LockedMoveError> {
+        let mut res = State::default();
+        self.apply_legal(&mut res)?;
+        Ok(res)
+    }
+
+    /// Get the algorithm that undoes this one.
+    ///
+    /// Since a face's own lock does not change when it is turned, every
+    /// legal move can be undone, so if an algorithm is legal from a state,
+    /// its inverse is legal from the resulting state.
+    pub fn inverse(&self) -> Algo {
+        Algo(self.0.iter().rev().map(|m| m.inverse()).collect())
+    }
+
+    /// Get the length of the algorithm in the given metric.
+    pub fn cost(&self, metric: Metric) -> usize {
+        self.0.iter().map(|m| metric.cost(*m) as usize).sum()
+    }
 }
 
 impl Display for Algo {
@@ -124,6 +165,136 @@ impl FromStr for Algo {
     }
 }
 
+/// A way of counting the length of an algorithm.
+///
+/// The default is FaceTurn.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Metric {
+    /// Every move counts once, including half turns.
+    #[default]
+    FaceTurn,
+
+    /// Half turns count as two moves.
+    QuarterTurn
+}
+
+impl Metric {
+    /// Get the cost of a single move.
+    pub fn cost(&self, m: Move) -> u8 {
+        match (self, m.turns) {
+            (&Metric::QuarterTurn, Turns::Double) => 2,
+            _ => 1
+        }
+    }
+}
+
+impl Display for Metric {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", match self {
+            &Metric::FaceTurn => "FTM",
+            &Metric::QuarterTurn => "QTM"
+        })
+    }
+}
+
+/// A set of moves, such as the moves of the `<R, U>` subgroup.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct MoveSet(u32);
+
+impl MoveSet {
+    /// Create a set containing every move.
+    pub fn all() -> MoveSet {
+        MoveSet((1 << ALL_MOVES.len()) - 1)
+    }
+
+    /// Create a set containing no moves.
+    pub fn empty() -> MoveSet {
+        MoveSet(0)
+    }
+
+    /// Create a set containing every turn of the given faces.
+    pub fn faces(faces: &[Face]) -> MoveSet {
+        let mut res = MoveSet::empty();
+        for m in ALL_MOVES.iter().filter(|m| faces.contains(&m.face)) {
+            res.insert(*m);
+        }
+        res
+    }
+
+    /// Create a set containing the half turn of every face.
+    pub fn half_turns() -> MoveSet {
+        let mut res = MoveSet::empty();
+        for m in ALL_MOVES.iter().filter(|m| m.turns == Turns::Double) {
+            res.insert(*m);
+        }
+        res
+    }
+
+    /// Add a move to the set.
+    pub fn insert(&mut self, m: Move) {
+        self.0 |= 1 << move_index(m);
+    }
+
+    /// Check if the set contains a move.
+    pub fn contains(&self, m: Move) -> bool {
+        self.0 & (1 << move_index(m)) != 0
+    }
+
+    /// Create the smallest set containing these moves and their inverses.
+    pub fn with_inverses(&self) -> MoveSet {
+        let mut res = *self;
+        for m in self.moves() {
+            res.insert(m.inverse());
+        }
+        res
+    }
+
+    /// Get the moves in the set, in the order of `ALL_MOVES`.
+    pub fn moves(&self) -> Vec<Move> {
+        ALL_MOVES.iter().filter(|m| self.contains(**m)).cloned().collect()
+    }
+}
+
+impl Default for MoveSet {
+    /// Create the set of all moves.
+    fn default() -> MoveSet {
+        MoveSet::all()
+    }
+}
+
+impl Display for MoveSet {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        for (i, m) in self.moves().iter().enumerate() {
+            if i != 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", m)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MoveSet {
+    type Err = ParseMoveError;
+
+    /// Parse a comma-separated list of moves.
+    ///
+    /// A face name on its own (e.g. "R") stands for every turn of that face,
+    /// so "R,U" is the `<R, U>` subgroup and "R2,U" only allows half turns of
+    /// the R face.
+    fn from_str(s: &str) -> Result<MoveSet, ParseMoveError> {
+        let mut res = MoveSet::empty();
+        for token in s.split(',').map(|t| t.trim()) {
+            if let Some(m) = ALL_MOVES.iter().find(|m| format!("{}", m.face) == token) {
+                res.0 |= MoveSet::faces(&[m.face]).0;
+            } else {
+                res.insert(token.parse()?);
+            }
+        }
+        Ok(res)
+    }
+}
+
 /// An error from parsing a move.
 #[derive(Clone, Debug)]
 pub struct ParseMoveError {
@@ -157,6 +328,27 @@ impl Error for ParseMoveError {
     }
 }
 
+/// An error from applying a move that turns a locked face.
+///
+/// The index counts from zero.
+#[derive(Clone, Debug)]
+pub struct LockedMoveError {
+    pub index: usize,
+    pub m: Move
+}
+
+impl Display for LockedMoveError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "move {} ({}) turns the locked {} face", self.index + 1, self.m, self.m.face)
+    }
+}
+
+impl Error for LockedMoveError {
+    fn description(&self) -> &str {
+        "move turns a locked face"
+    }
+}
+
 /// The number of times a face is turned (once, twice, or thrice).
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Turns {
@@ -235,6 +427,25 @@ impl Turns {
     }
 }
 
+/// Get the index of a move in `ALL_MOVES`.
+fn move_index(m: Move) -> usize {
+    use Face::*;
+    let face_idx = match m.face {
+        U => 0,
+        D => 1,
+        F => 2,
+        B => 3,
+        R => 4,
+        L => 5
+    };
+    let turns_idx = match m.turns {
+        Turns::Clockwise => 0,
+        Turns::Double => 1,
+        Turns::Counter => 2
+    };
+    face_idx * 3 + turns_idx
+}
+
 /// Get the stickers that are adjacent to a face.
 ///
 /// The stickers are grouped by 3's from the same adjacent face.
@@ -300,6 +511,61 @@ mod tests {
         assert_eq!(format!("{}", algo), "R' U D' F2 L' B2");
     }
 
+    /// Test that moves which turn locked faces are rejected.
+    #[test]
+    fn legal_state() {
+        let algo: Algo = "B D2".parse().unwrap();
+        let state = algo.legal_state().unwrap();
+        assert_eq!(state, algo.state());
+
+        let face = state.locked_faces()[0];
+        let illegal: Algo = format!("B D2 {}", face).parse().unwrap();
+        let err = illegal.legal_state().unwrap_err();
+        assert_eq!((err.index, err.m.face), (2, face));
+
+        let mut applied = state.clone();
+        assert!(Algo(vec![err.m]).apply_legal(&mut applied).is_err());
+        assert_eq!(applied, state);
+    }
+
+    /// Test algorithm lengths in different metrics.
+    #[test]
+    fn algo_cost() {
+        let algo: Algo = "R U2 D' F2 B2 L".parse().unwrap();
+        assert_eq!(algo.cost(Metric::FaceTurn), 6);
+        assert_eq!(algo.cost(Metric::QuarterTurn), 9);
+    }
+
+    /// Test that inverting an algorithm undoes it.
+    #[test]
+    fn algo_inverse() {
+        let algo: Algo = "R U2 D' F".parse().unwrap();
+        assert_eq!(algo.inverse(), "F' D U2 R'".parse().unwrap());
+        let mut state = algo.state();
+        algo.inverse().apply(&mut state);
+        assert!(state.is_solved());
+    }
+
+    /// Test move set parsing.
+    #[test]
+    fn parse_move_set() {
+        let actual: MoveSet = "R, U2,U'".parse().unwrap();
+        let expected = vec![
+            Move{face: Face::U, turns: Turns::Double},
+            Move{face: Face::U, turns: Turns::Counter},
+            Move{face: Face::R, turns: Turns::Clockwise},
+            Move{face: Face::R, turns: Turns::Double},
+            Move{face: Face::R, turns: Turns::Counter}
+        ];
+        assert_eq!(actual.moves(), expected);
+        assert_eq!(format!("{}", actual), "U2,U',R,R2,R'");
+
+        assert_eq!(MoveSet::from_str("U2,D2,F2,B2,R2,L2").unwrap(), MoveSet::half_turns());
+        assert_eq!(MoveSet::from_str("U,D,F,B,R,L").unwrap(), MoveSet::all());
+        assert!(MoveSet::from_str("R,").is_err());
+        assert!(MoveSet::from_str("RU").is_err());
+    }
+
     /// Test U moves.
     #[test]
     fn u_move() {
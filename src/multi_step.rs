@@ -2,57 +2,104 @@
 
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::collections::HashSet;
 use std::error::Error;
-use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, channel};
 use std::thread::spawn;
+use std::time::{Duration, Instant};
 
 use super::heuristic::{Heuristic, MaxHeuristic, ProjHeuristic};
-use super::moves::Algo;
+use super::moves::{Algo, Metric, MoveSet};
 use super::proj::{ArrowAxisProj, CoFbProj, CoRlProj, CoUdProj, CornerProj, LockProj, PairProj,
     Proj};
-use super::solve::{proj_solve, solve};
 use super::state::State;
+use super::step::{FallbackStep, ProjStep, SolveStep, Step};
 
 /// A multi-step solver.
+///
+/// The solver runs a pipeline of steps in order, where each step starts from
+/// the state left behind by the previous one.
 pub struct MultiStep {
-    pub arrow: ProjHeuristic<ArrowAxisProj>,
-    pub co_fb: ProjHeuristic<CoFbProj>,
-    pub co_rl: ProjHeuristic<CoRlProj>,
-    pub co_ud: ProjHeuristic<CoUdProj>,
-    pub corner: ProjHeuristic<CornerProj>,
-    pub lock: ProjHeuristic<LockProj>,
+    pub steps: Vec<Box<Step>>
 }
 
 impl MultiStep {
+    /// Create a MultiStep solver from a pipeline of steps.
+    ///
+    /// The last step should leave the puzzle solved.
+    pub fn new(steps: Vec<Box<Step>>) -> MultiStep {
+        MultiStep{steps: steps}
+    }
+
     /// Generate a MultiStep solver with reasonable default settings.
+    ///
+    /// The steps are:
+    ///  1. Unlock the faces and orient the edges (`ArrowAxisProj`).
+    ///  2. Orient the corners (`CoFbProj`, `CoRlProj`, `CoUdProj`).
+    ///  3. Solve the corners (`CornerProj`).
+    ///  4. Solve the rest of the puzzle.
     pub fn generate_default() -> MultiStep {
-        macro_rules! generate_table {
-            ( $proj:ident, $depth:expr ) => {
-                {
-                    let (tx, rx) = channel();
-                    spawn(move || {
-                        tx.send(ProjHeuristic::<$proj>::generate($depth)).unwrap();
-                    });
-                    rx
-                }
-            }
-        }
+        MultiStep::generate_default_with_corners().0
+    }
 
-        let arrow_rx = generate_table!(ArrowAxisProj, 7);
-        let co_fb_rx = generate_table!(CoFbProj, 7);
-        let co_rl_rx = generate_table!(CoRlProj, 7);
-        let co_ud_rx = generate_table!(CoUdProj, 7);
-        let corner_rx = generate_table!(CornerProj, 7);
-        let lock_rx = generate_table!(LockProj, 8);
-
-        MultiStep{
-            arrow: arrow_rx.recv().unwrap(),
-            co_fb: co_fb_rx.recv().unwrap(),
-            co_rl: co_rl_rx.recv().unwrap(),
-            co_ud: co_ud_rx.recv().unwrap(),
-            corner: corner_rx.recv().unwrap(),
-            lock: lock_rx.recv().unwrap()
-        }
+    /// Generate the default pipeline like generate_default(), along with its
+    /// `CornerProj` table, so that other solvers can share the table.
+    pub(crate) fn generate_default_with_corners() -> (MultiStep, Arc<Heuristic>) {
+        let arrow_rx = generate_table::<ArrowAxisProj>(7);
+        let co_fb_rx = generate_table::<CoFbProj>(7);
+        let co_rl_rx = generate_table::<CoRlProj>(7);
+        let co_ud_rx = generate_table::<CoUdProj>(7);
+        let corner_rx = generate_table::<CornerProj>(7);
+        let lock_rx = generate_table::<LockProj>(8);
+
+        let arrow = arrow_rx.recv().unwrap();
+        let lock = lock_rx.recv().unwrap();
+
+        let mut steps: Vec<Box<Step>> = vec![
+            Box::new(FallbackStep::new(
+                Box::new(ProjStep::<ArrowAxisProj>::new("ArrowAxisProj", arrow.clone(), 13)),
+                vec![
+                    Box::new(ProjStep::<LockProj>::new("LockProj", lock, 12)),
+                    Box::new(ProjStep::<ArrowAxisProj>::new("ArrowAxisProj", arrow.clone(), 254))
+                ]
+            ))
+        ];
+        let corner = corner_rx.recv().unwrap();
+        steps.extend(corner_steps(
+            arrow,
+            co_fb_rx.recv().unwrap(),
+            co_rl_rx.recv().unwrap(),
+            co_ud_rx.recv().unwrap(),
+            corner.clone()
+        ));
+        (MultiStep::new(steps), corner)
+    }
+
+    /// Generate a MultiStep solver that solves the corners before orienting
+    /// the edges.
+    ///
+    /// The steps are:
+    ///  1. Solve the corners (`CornerProj`). The faces may still be locked
+    ///     afterwards.
+    ///  2. Unlock the faces and orient the edges, keeping the corners solved
+    ///     (`ArrowAxisProj`).
+    ///  3. Solve the rest of the puzzle.
+    pub fn generate_corners_first() -> MultiStep {
+        let arrow_rx = generate_table::<ArrowAxisProj>(7);
+        let corner_rx = generate_table::<CornerProj>(7);
+        let arrow = arrow_rx.recv().unwrap();
+        let corner = corner_rx.recv().unwrap();
+
+        type ComboProj = PairProj<ArrowAxisProj, CornerProj>;
+        let combo: Arc<Heuristic> = Arc::new(MaxHeuristic(vec![arrow, corner.clone()]));
+
+        MultiStep::new(vec![
+            Box::new(ProjStep::<CornerProj>::new("CornerProj", corner, 254)),
+            Box::new(ProjStep::<ComboProj>::new("ArrowAxisProj+CornerProj", combo.clone(), 254)),
+            Box::new(SolveStep::new(combo, 254))
+        ])
     }
 
     /// Find a solution for the state.
@@ -60,70 +107,197 @@ impl MultiStep {
     /// Returns both the complete solution, and a decomposition of the
     /// solution into its component steps.
     pub fn solve(&self, s: &State) -> Result<(Algo, Vec<Algo>), MultiStepError> {
-        use MultiStepError::*;
-        let (mut parts, new_state) = match MultiStep::step::<ArrowAxisProj>(s, &self.arrow, 14) {
-            Some((algo, new_state)) => (vec![algo], new_state),
-            None => {
-                let (a1, s1) = MultiStep::step::<LockProj>(s, &self.lock, 13)
-                    .ok_or(InvalidEdges)?;
-                let (a2, s2) = MultiStep::step::<ArrowAxisProj>(&s1, &self.arrow, 255)
-                    .ok_or(InvalidEdges)?;
-                (vec![a1, a2], s2)
-            }
-        };
-
-        type Combo1Proj = PairProj<PairProj<ArrowAxisProj, CoFbProj>,
-                                   PairProj<CoRlProj, CoUdProj>>;
-        let combo1 = MaxHeuristic::<&Heuristic>(vec![&self.arrow, &self.co_fb, &self.co_rl,
-            &self.co_ud]);
-        let (algo, new_state) = MultiStep::step::<Combo1Proj>(&new_state, &combo1, 255)
-            .ok_or(InvalidCorners)?;
-        parts.push(algo);
-
-        type Combo2Proj = PairProj<ArrowAxisProj, CornerProj>;
-        let combo2 = MaxHeuristic::<&Heuristic>(vec![&self.arrow, &self.corner]);
-        let (algo, new_state) = MultiStep::step::<Combo2Proj>(&new_state, &combo1, 255)
-            .ok_or(InvalidState)?;
-        parts.push(algo);
-
-        for i in 0..255 {
-            if let Some(algo) = solve(&new_state, &combo2, i) {
-                parts.push(algo);
-                let combined = (&parts).iter().flat_map(|alg| alg.0.clone()).collect();
-                return Ok((Algo(combined), parts));
+        self.solve_named(s).map(strip_names)
+    }
+
+    /// Find a solution for the state, like solve(), but name each part of the
+    /// solution after the step that produced it.
+    pub fn solve_named(&self, s: &State) -> Result<(Algo, Vec<(String, Algo)>), MultiStepError> {
+        self.solve_cancel(s, &AtomicBool::new(false), None)
+    }
+
+    /// Find a solution for the state like solve_named(), but give up soon
+    /// after cancel is set or the deadline has passed.
+    ///
+    /// A solve that gives up fails with MultiStepError::Cancelled.
+    pub fn solve_cancel(
+        &self,
+        s: &State,
+        cancel: &AtomicBool,
+        deadline: Option<Instant>
+    ) -> Result<(Algo, Vec<(String, Algo)>), MultiStepError> {
+        let mut state = s.clone();
+        let mut parts = Vec::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            let step_parts = match step.solve(&state, cancel, deadline) {
+                Some(step_parts) => step_parts,
+                None if is_cancelled(cancel, deadline) => {
+                    return Err(MultiStepError::Cancelled{
+                        partial: combine_parts(parts).0,
+                        state: Box::new(state)
+                    });
+                },
+                None => return Err(MultiStepError::step_failed(i, &**step, &parts, state))
+            };
+            for part in step_parts {
+                part.apply(&mut state);
+                parts.push((step.name(), part));
             }
         }
+        if !state.is_solved() {
+            return Err(MultiStepError::Unsolved{
+                partial: combine_parts(parts).0,
+                state: Box::new(state)
+            });
+        }
+        Ok(combine_parts(parts))
+    }
 
-        Err(InvalidState)
+    /// Find a solution for the state using a beam search over the steps.
+    ///
+    /// Rather than committing to the first solution of each step, this keeps
+    /// the width shortest partial solutions after every step and continues
+    /// each of them through the remaining steps.
+    /// The shortest complete solution is returned.
+    ///
+    /// Once the time limit has passed, the candidate searches are cut off,
+    /// and the remaining steps are solved greedily from the best partial
+    /// solution so far.
+    /// Soon after cancel is set, the solve fails with
+    /// MultiStepError::Cancelled.
+    pub fn solve_beam(
+        &self,
+        s: &State,
+        width: usize,
+        time_limit: Duration,
+        cancel: &AtomicBool
+    ) -> Result<(Algo, Vec<Algo>), MultiStepError> {
+        self.solve_beam_named(s, width, time_limit, cancel).map(strip_names)
     }
 
-    fn step<P: Proj>(
+    /// Find a solution for the state like solve_beam(), but name each part of
+    /// the solution after the step that produced it.
+    pub fn solve_beam_named(
+        &self,
         s: &State,
-        h: &Heuristic,
-        upper_bound: u8
-    ) -> Option<(Algo, State)> {
-        for i in 0..upper_bound {
-            if let Some(solution) = proj_solve::<P, _>(s, h, i) {
-                let mut new_state = s.clone();
-                solution.apply(&mut new_state);
-                return Some((solution, new_state));
+        width: usize,
+        time_limit: Duration,
+        cancel: &AtomicBool
+    ) -> Result<(Algo, Vec<(String, Algo)>), MultiStepError> {
+        let deadline = Instant::now() + time_limit;
+        let mut beam = vec![(Vec::<(String, Algo)>::new(), s.clone())];
+        for (i, step) in self.steps.iter().enumerate() {
+            let mut next = Vec::new();
+            for &(ref parts, ref state) in &beam {
+                if Instant::now() >= deadline {
+                    break;
+                }
+                for candidate in step.candidates(state, width, cancel, Some(deadline)) {
+                    next.push(extend_parts(&**step, parts, state, candidate));
+                }
+            }
+            if next.len() == 0 && Instant::now() >= deadline {
+                // The time limit cut off the candidate searches, so continue
+                // greedily from the best partial solution.
+                beam.truncate(1);
+                let (ref parts, ref state) = beam[0];
+                if let Some(candidate) = step.solve(state, cancel, None) {
+                    next.push(extend_parts(&**step, parts, state, candidate));
+                }
             }
+            if next.len() == 0 && cancel.load(Ordering::SeqCst) {
+                let (parts, state) = beam.swap_remove(0);
+                return Err(MultiStepError::Cancelled{
+                    partial: combine_parts(parts).0,
+                    state: Box::new(state)
+                });
+            } else if next.len() == 0 {
+                let (parts, state) = beam.swap_remove(0);
+                return Err(MultiStepError::step_failed(i, &**step, &parts, state));
+            }
+
+            // Keep the shortest partial solution for each distinct state.
+            next.sort_by_key(|&(ref parts, _)| parts.iter().map(|p| p.0.len()).sum::<usize>());
+            let mut seen = HashSet::new();
+            beam = next.into_iter().filter(|&(_, ref state)| seen.insert(state.clone()))
+                .take(width)
+                .collect();
         }
-        None
+        if let Some(i) = beam.iter().position(|&(_, ref state)| state.is_solved()) {
+            return Ok(combine_parts(beam.swap_remove(i).0));
+        }
+        let (parts, state) = beam.swap_remove(0);
+        Err(MultiStepError::Unsolved{partial: combine_parts(parts).0, state: Box::new(state)})
     }
 }
 
 /// An error describing why a MultiStep solve failed.
+///
+/// Errors carry the moves found before the failure and the state they left
+/// the puzzle in, so that failures can be diagnosed.
+/// The state is boxed to keep results small.
 #[derive(Debug, Eq, PartialEq)]
 pub enum MultiStepError {
-    InvalidCorners,
-    InvalidEdges,
-    InvalidState
+    /// A step could not be completed within its depth budget.
+    StepFailed{
+        index: usize,
+        name: String,
+        depth: u8,
+        partial: Algo,
+        state: Box<State>
+    },
+
+    /// Every step succeeded, but the puzzle was not solved at the end.
+    Unsolved{partial: Algo, state: Box<State>},
+
+    /// The solve was cancelled or ran out of time before it was done.
+    Cancelled{partial: Algo, state: Box<State>}
+}
+
+impl MultiStepError {
+    fn step_failed(index: usize, step: &Step, parts: &[(String, Algo)], state: State) -> Self {
+        MultiStepError::StepFailed{
+            index: index,
+            name: step.name(),
+            depth: step.max_depth(),
+            partial: combine_parts(parts.to_vec()).0,
+            state: Box::new(state)
+        }
+    }
+
+    /// Get the moves that were found before the solve failed.
+    pub fn partial(&self) -> &Algo {
+        use MultiStepError::*;
+        match *self {
+            StepFailed{ref partial, ..} => partial,
+            Unsolved{ref partial, ..} => partial,
+            Cancelled{ref partial, ..} => partial
+        }
+    }
+
+    /// Get the state of the puzzle when the solve failed.
+    pub fn state(&self) -> &State {
+        use MultiStepError::*;
+        match *self {
+            StepFailed{ref state, ..} => state,
+            Unsolved{ref state, ..} => state,
+            Cancelled{ref state, ..} => state
+        }
+    }
 }
 
 impl Display for MultiStepError {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        write!(f, "{}", self.description())
+        use MultiStepError::*;
+        match self {
+            &StepFailed{index, ref name, depth, ref partial, ..} => {
+                write!(f, "step {} ({}) failed within depth {} after {} moves", index + 1, name,
+                    depth, partial.0.len())
+            },
+            &Unsolved{ref partial, ..} | &Cancelled{ref partial, ..} => {
+                write!(f, "{} after {} moves", self.description(), partial.0.len())
+            }
+        }
     }
 }
 
@@ -131,9 +305,174 @@ impl Error for MultiStepError {
     fn description(&self) -> &str {
         use MultiStepError::*;
         match self {
-            &InvalidCorners => "invalid corners",
-            &InvalidEdges => "invalid edges",
-            &InvalidState => "invalid cube state"
+            &StepFailed{..} => "step failed",
+            &Unsolved{..} => "pipeline did not solve the puzzle",
+            &Cancelled{..} => "solve was cancelled"
+        }
+    }
+}
+
+/// Create the steps of the default pipeline that come after the edges have
+/// been oriented.
+pub(crate) fn corner_steps(
+    arrow: Arc<Heuristic>,
+    co_fb: Arc<Heuristic>,
+    co_rl: Arc<Heuristic>,
+    co_ud: Arc<Heuristic>,
+    corner: Arc<Heuristic>
+) -> Vec<Box<Step>> {
+    type Combo1Proj = PairProj<PairProj<ArrowAxisProj, CoFbProj>, PairProj<CoRlProj, CoUdProj>>;
+    type Combo2Proj = PairProj<ArrowAxisProj, CornerProj>;
+    let combo1: Arc<Heuristic> = Arc::new(MaxHeuristic(vec![arrow.clone(), co_fb, co_rl,
+        co_ud]));
+    let combo2: Arc<Heuristic> = Arc::new(MaxHeuristic(vec![arrow, corner]));
+    vec![
+        Box::new(ProjStep::<Combo1Proj>::new("ArrowAxisProj+CoProj", combo1.clone(), 254)),
+        Box::new(ProjStep::<Combo2Proj>::new("ArrowAxisProj+CornerProj", combo1, 254)),
+        Box::new(SolveStep::new(combo2, 254))
+    ]
+}
+
+/// Check if a search has been cancelled or has run out of time.
+fn is_cancelled(cancel: &AtomicBool, deadline: Option<Instant>) -> bool {
+    cancel.load(Ordering::SeqCst) || deadline.map(|d| Instant::now() >= d).unwrap_or(false)
+}
+
+/// Add a candidate for a step to a partial solution.
+fn extend_parts(
+    step: &Step,
+    parts: &[(String, Algo)],
+    state: &State,
+    candidate: Vec<Algo>
+) -> (Vec<(String, Algo)>, State) {
+    let mut new_parts = parts.to_vec();
+    let mut new_state = state.clone();
+    for part in candidate {
+        part.apply(&mut new_state);
+        new_parts.push((step.name(), part));
+    }
+    (new_parts, new_state)
+}
+
+/// Join the parts of a solution into one algorithm.
+fn combine_parts(parts: Vec<(String, Algo)>) -> (Algo, Vec<(String, Algo)>) {
+    let combined = (&parts).iter().flat_map(|&(_, ref alg)| alg.0.clone()).collect();
+    (Algo(combined), parts)
+}
+
+/// Drop the step names from a named solution.
+pub(crate) fn strip_names((solution, parts): (Algo, Vec<(String, Algo)>)) -> (Algo, Vec<Algo>) {
+    (solution, parts.into_iter().map(|(_, part)| part).collect())
+}
+
+/// Generate a heuristic table in the background.
+pub(crate) fn generate_table<P: Proj + 'static>(depth: u8) -> Receiver<Arc<Heuristic>> {
+    let (tx, rx) = channel();
+    spawn(move || {
+        let table = ProjHeuristic::<P>::generate(depth, MoveSet::all(), Metric::FaceTurn);
+        tx.send(Arc::new(table) as Arc<Heuristic>).unwrap();
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heuristic::NopHeuristic;
+
+    /// Test a custom pipeline on a short scramble.
+    #[test]
+    fn custom_pipeline() {
+        let nop: Arc<Heuristic> = Arc::new(NopHeuristic());
+        let multi = MultiStep::new(vec![
+            Box::new(ProjStep::<LockProj>::new("LockProj", nop.clone(), 5)),
+            Box::new(SolveStep::new(nop.clone(), 5))
+        ]);
+        let algo: Algo = "B D2 B' U2 L2".parse().unwrap();
+        let (solution, parts) = multi.solve(&algo.state()).unwrap();
+        assert_eq!(parts.len(), 2);
+        let (_, named) = multi.solve_named(&algo.state()).unwrap();
+        let names: Vec<&str> = named.iter().map(|&(ref name, _)| name.as_str()).collect();
+        assert_eq!(names, ["LockProj", "solved"]);
+        let mut state = algo.state();
+        solution.apply(&mut state);
+        assert!(state.is_solved());
+
+        let never = AtomicBool::new(false);
+        let (beam_solution, _) = multi.solve_beam(&algo.state(), 4, Duration::from_secs(1000),
+            &never).unwrap();
+        assert!(beam_solution.0.len() <= solution.0.len());
+        let mut state = algo.state();
+        beam_solution.apply(&mut state);
+        assert!(state.is_solved());
+
+        let failing = MultiStep::new(vec![Box::new(SolveStep::new(nop.clone(), 1))]);
+        assert_eq!(failing.solve(&algo.state()), Err(MultiStepError::StepFailed{
+            index: 0,
+            name: "solved".to_owned(),
+            depth: 1,
+            partial: Algo(Vec::new()),
+            state: Box::new(algo.state())
+        }));
+
+        let lock_only = MultiStep::new(vec![
+            Box::new(ProjStep::<LockProj>::new("LockProj", nop.clone(), 5)),
+            Box::new(SolveStep::new(nop, 1))
+        ]);
+        let longer: Algo = "U D R L".parse().unwrap();
+        match lock_only.solve(&longer.state()) {
+            Err(MultiStepError::StepFailed{index, depth, ref partial, ref state, ..}) => {
+                assert_eq!((index, depth), (1, 1));
+                let mut expected = longer.state();
+                partial.apply(&mut expected);
+                assert_eq!(**state, expected);
+            },
+            res => panic!("unexpected result: {:?}", res)
+        }
+    }
+
+    /// Test that the time limit cuts off the candidate searches of a beam.
+    #[test]
+    fn beam_time_limit() {
+        let nop: Arc<Heuristic> = Arc::new(NopHeuristic());
+        let multi = MultiStep::new(vec![
+            Box::new(ProjStep::<LockProj>::new("LockProj", nop.clone(), 5)),
+            Box::new(SolveStep::new(nop.clone(), 5))
+        ]);
+        let algo: Algo = "B D2 B' U2 L2".parse().unwrap();
+        let never = AtomicBool::new(false);
+        let (solution, parts) = multi.solve_beam(&algo.state(), 4, Duration::from_secs(0),
+            &never).unwrap();
+        assert_eq!(parts.len(), 2);
+        let mut state = algo.state();
+        solution.apply(&mut state);
+        assert!(state.is_solved());
+
+        // Without a deadline, this search would take far too long.
+        let slow = SolveStep::new(nop, 254);
+        let hard: Algo = "D' L2 D L' D2 F2 L2 F' L D'".parse().unwrap();
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(100);
+        assert!(slow.candidates(&hard.state(), 4, &never, Some(deadline)).is_empty());
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        let cancel = AtomicBool::new(true);
+        match multi.solve_beam(&algo.state(), 4, Duration::from_secs(1000), &cancel) {
+            Err(MultiStepError::Cancelled{..}) => (),
+            res => panic!("unexpected result: {:?}", res)
         }
     }
+
+    /// Test the corners-first pipeline on a short scramble.
+    #[test]
+    fn corners_first() {
+        let multi = MultiStep::generate_corners_first();
+        let algo: Algo = "D' L2 D L' D2 F2 L2 F' L D'".parse().unwrap();
+        let (solution, named) = multi.solve_named(&algo.state()).unwrap();
+        let names: Vec<&str> = named.iter().map(|&(ref name, _)| name.as_str()).collect();
+        assert_eq!(names, ["CornerProj", "ArrowAxisProj+CornerProj", "solved"]);
+        let mut state = algo.state();
+        solution.apply(&mut state);
+        assert!(state.is_solved());
+    }
 }
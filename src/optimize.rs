@@ -0,0 +1,79 @@
+//! Shortening solutions after they have been found.
+
+use super::moves::{Algo, Metric, MoveSet};
+use super::solve::solve_between;
+use super::state::State;
+
+/// Shorten an algorithm by replacing windows of moves with optimal sequences
+/// that have the same effect.
+///
+/// The algorithm is applied to the start state.
+/// Every window of up to `window` consecutive moves is replaced by the
+/// shortest sequence of moves between the same two states, so the result
+/// leaves the puzzle in the same state as the original algorithm.
+/// Moves of locked faces are never introduced.
+///
+/// Each window is searched without a heuristic, so large windows are slow.
+pub fn optimize(start: &State, algo: &Algo, window: usize) -> Algo {
+    let mut moves = algo.0.clone();
+    let mut state = start.clone();
+    let mut i = 0;
+    while i < moves.len() {
+        let end = (i + window).min(moves.len());
+        let mut goal = state.clone();
+        for m in &moves[i..end] {
+            m.apply(&mut goal);
+        }
+        if let Some(replacement) = shorten(&state, &goal, end - i) {
+            moves.splice(i..end, replacement.0);
+
+            // The new moves may enable improvements in earlier windows.
+            i = i.saturating_sub(window.saturating_sub(1));
+            state = start.clone();
+            for m in &moves[0..i] {
+                m.apply(&mut state);
+            }
+        } else {
+            moves[i].apply(&mut state);
+            i += 1;
+        }
+    }
+    Algo(moves)
+}
+
+/// Find the shortest sequence of fewer than length moves between two states.
+///
+/// Searches are limited to 255 moves, so longer windows can only be replaced
+/// by sequences of up to 255 moves.
+fn shorten(start: &State, goal: &State, length: usize) -> Option<Algo> {
+    for depth in 0..length.min(256) {
+        let res = solve_between(start, goal, MoveSet::all(), Metric::FaceTurn, depth as u8);
+        if res.is_some() {
+            return res;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test shortening algorithms with wasted moves.
+    #[test]
+    fn wasted_moves() {
+        let start: State = "B D2 B' U2 L2".parse::<Algo>().unwrap().state();
+        let long: Algo = "L2 U2 B U U' D2 B'".parse().unwrap();
+        let actual = optimize(&start, &long, 3);
+        assert_eq!(actual, "L2 U2 B D2 B'".parse().unwrap());
+
+        let optimal: Algo = "L2 U2 B D2 B'".parse().unwrap();
+        assert_eq!(optimize(&start, &optimal, 5), optimal);
+
+        let mut expected = start.clone();
+        long.apply(&mut expected);
+        let mut state = start.clone();
+        optimize(&start, &long, 2).apply(&mut state);
+        assert!(state == expected);
+    }
+}
@@ -0,0 +1,170 @@
+//! Drawing states in a terminal.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use super::state::{Direction, Face, State, Sticker};
+
+/// A way of drawing stickers as text.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TextStyle {
+    /// Stickers are written like in State's Display output (e.g. `Uc'`).
+    Ascii,
+
+    /// Stickers are colored blocks, using ANSI escape codes, with arrow
+    /// glyphs for their directions.
+    Ansi
+}
+
+impl FromStr for TextStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TextStyle, String> {
+        match s {
+            "ascii" => Ok(TextStyle::Ascii),
+            "ansi" => Ok(TextStyle::Ansi),
+            _ => Err(format!("unknown text style: {}", s))
+        }
+    }
+}
+
+impl Display for TextStyle {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &TextStyle::Ascii => write!(f, "ascii"),
+            &TextStyle::Ansi => write!(f, "ansi")
+        }
+    }
+}
+
+/// Draw the state as an unfolded cube net.
+///
+/// The U face is drawn above F, and D below it, with L, F, R and B in the
+/// middle row.
+/// Every face is labeled, and locked faces are marked as such.
+pub fn render_net(state: &State, style: TextStyle) -> String {
+    use Face::*;
+    let width = 3 * cell_width(style) + 1;
+    let mut lines = Vec::new();
+    for row in &[vec![None, Some(U)], vec![Some(L), Some(F), Some(R), Some(B)],
+                 vec![None, Some(D)]] {
+        let mut label = String::new();
+        for face in row {
+            let name = match face {
+                &Some(face) if state.is_locked(face) => format!("{} locked", face),
+                &Some(face) => format!("{}", face),
+                &None => String::new()
+            };
+            label.push_str(&format!("{:<1$}", name, width));
+        }
+        lines.push(label);
+        for y in 0..3 {
+            let mut line = String::new();
+            for face in row {
+                match face {
+                    &Some(face) => {
+                        for x in 0..3 {
+                            line.push_str(&render_sticker(net_sticker(state, face, x, y), style));
+                        }
+                        line.push(' ');
+                    },
+                    &None => line.push_str(&" ".repeat(width))
+                }
+            }
+            lines.push(line);
+        }
+    }
+    let mut res = String::new();
+    for line in lines {
+        res.push_str(line.trim_end());
+        res.push('\n');
+    }
+    res
+}
+
+/// Get the sticker at a position on a face, including the center.
+fn net_sticker(state: &State, face: Face, x: usize, y: usize) -> Sticker {
+    let idx = y * 3 + x;
+    if idx == 4 {
+        Sticker{face: face, direction: Direction::Neutral}
+    } else if idx < 4 {
+        state.face(face)[idx]
+    } else {
+        state.face(face)[idx - 1]
+    }
+}
+
+/// Get the number of characters used to draw a sticker.
+fn cell_width(style: TextStyle) -> usize {
+    match style {
+        TextStyle::Ascii => 4,
+        TextStyle::Ansi => 3
+    }
+}
+
+/// Draw a sticker as cell_width() characters.
+fn render_sticker(sticker: Sticker, style: TextStyle) -> String {
+    match style {
+        TextStyle::Ascii => {
+            format!("{:<4}", format!("{}{}", sticker.face, match sticker.direction {
+                Direction::Clockwise => "c",
+                Direction::Counter => "c'",
+                Direction::Neutral => ""
+            }))
+        },
+        TextStyle::Ansi => {
+            let glyph = match sticker.direction {
+                Direction::Clockwise => "↻",
+                Direction::Counter => "↺",
+                Direction::Neutral => " "
+            };
+            format!("\x1b[{}m {} \x1b[0m", ansi_color(sticker.face), glyph)
+        }
+    }
+}
+
+/// Get the ANSI codes for a face's background color, along with a
+/// foreground color that is readable on top of it.
+fn ansi_color(face: Face) -> &'static str {
+    use Face::*;
+    match face {
+        U => "30;43",
+        D => "97;41",
+        F => "97;44",
+        B => "30;42",
+        R => "97;40",
+        L => "30;107"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use moves::Algo;
+
+    /// Test drawing a net in ASCII.
+    #[test]
+    fn ascii_net() {
+        let actual = render_net(&State::default(), TextStyle::Ascii);
+        let expected = [
+            "             U",
+            "             U   Uc' U",
+            "             U   U   U",
+            "             U   Uc' U",
+            "L            F            R            B",
+            "L   Lc' L    F   F   F    R   Rc  R    B   B   B",
+            "L   L   L    Fc  F   Fc   R   R   R    Bc' B   Bc'",
+            "L   Lc' L    F   F   F    R   Rc  R    B   B   B",
+            "             D",
+            "             D   Dc  D",
+            "             D   D   D",
+            "             D   Dc  D",
+            ""
+        ].join("\n");
+        assert_eq!(actual, expected);
+
+        let locked = render_net(&"F".parse::<Algo>().unwrap().state(), TextStyle::Ascii);
+        assert!(locked.contains("\nL locked     F            R locked     B\n"));
+    }
+}
@@ -1,49 +1,285 @@
 //! Generating valid scrambles.
 
-use super::rand::random;
+use std::collections::HashSet;
 
-use super::move_gen::MoveGen;
-use super::moves::{Algo, Move};
+use super::rand::{ChaChaRng, Rng, SeedableRng, thread_rng};
+
+use super::heuristic::Heuristic;
+use super::moves::{ALL_MOVES, Algo, Metric, Move, MoveSet};
+use super::multi_step::MultiStep;
+use super::optimize::optimize;
+use super::proj::{LockProj, Proj};
+use super::solve::solve;
 use super::state::State;
 
+/// The number of unrestricted moves used to sample a random position.
+const RANDOM_WALK_LENGTH: usize = 1000;
+
+/// The number of states that must be reachable from a sampled position for
+/// it to pass the quick check for being trapped.
+const COMPONENT_LIMIT: usize = 1000;
+
+/// The window for shortening random-state scrambles with optimize().
+const OPTIMIZE_WINDOW: usize = 4;
+
+/// Create a random number generator for reproducible scrambles.
+///
+/// The generator algorithm is fixed, so a given seed always produces the
+/// same scrambles with the same version of this crate.
+pub fn seeded_rng(seed: u64) -> ChaChaRng {
+    ChaChaRng::from_seed(&[seed as u32, (seed >> 32) as u32])
+}
+
 /// Produce a scramble that is the given number of moves.
 pub fn scramble(moves: usize) -> Algo {
-    let mut state = State::default();
-    let mut scramble = Vec::new();
-    let status = scramble_search(&mut state, moves, &mut scramble, MoveGen::new());
+    scramble_with_rng(moves, &mut thread_rng())
+}
 
-    // It is always possible to generate a scramble of a given length.
-    // There are plenty of sequences S with a solution S' that is not the simple
-    // reverse of S.
-    // Thus, we can lengthen a scramble by |S|*2 moves by simply inserting S S'.
-    assert!(status);
+/// Produce a scramble that is the given number of moves, using the given
+/// source of randomness.
+///
+/// Moves are chosen one at a time, among the moves that turn an unlocked
+/// face other than the last one turned and leave at most four faces locked.
+/// A face's own lock does not change when it is turned, so with five faces
+/// locked the only legal moves would turn the last face again.
+/// There is always such a move, so the scramble never has to backtrack and
+/// the cost is linear in the number of moves (see the ignored test
+/// `no_dead_ends`, which checks every reachable pattern of locks).
+/// The last move may leave five faces locked, since no move follows it.
+pub fn scramble_with_rng<R: Rng>(moves: usize, rng: &mut R) -> Algo {
+    extend_scramble(&State::default(), moves, rng)
+}
 
+/// Produce a scramble from a state with at most four faces locked.
+fn extend_scramble<R: Rng>(start: &State, moves: usize, rng: &mut R) -> Algo {
+    let mut state = start.clone();
+    let mut scramble: Vec<Move> = Vec::new();
+    while scramble.len() < moves {
+        let max_locked = if scramble.len() + 1 < moves { 4 } else { 5 };
+        let last_face = scramble.last().map(|m| m.face);
+        let options: Vec<(Move, State)> = ALL_MOVES.iter()
+            .filter(|m| Some(m.face) != last_face && !state.is_locked(m.face))
+            .map(|m| {
+                let mut next = state.clone();
+                m.apply(&mut next);
+                (*m, next)
+            })
+            .filter(|&(_, ref next)| next.num_locked() <= max_locked)
+            .collect();
+        let (m, next) = options[random_index(rng, options.len())].clone();
+        scramble.push(m);
+        state = next;
+    }
     Algo(scramble)
 }
 
-fn scramble_search(
-    state: &mut State,
+/// Produce scrambles of the given number of moves until one of them leaves
+/// the puzzle in a state that satisfies accept.
+///
+/// Returns None if none of the first max_attempts scrambles were accepted,
+/// since some filters can never be satisfied.
+pub fn filtered_scramble<R: Rng, F: FnMut(&State) -> bool>(
     moves: usize,
-    history: &mut Vec<Move>,
-    gen: MoveGen
-) -> bool {
-    if moves == 0 {
-        return true;
-    }
-    let mut next_options: Vec<(MoveGen, Move)> = gen.into_iter().collect();
-    while next_options.len() > 0 {
-        let idx = random::<usize>() % next_options.len();
-        let (next_gen, m) = next_options.remove(idx);
-        if state.is_locked(m.face) {
+    rng: &mut R,
+    max_attempts: usize,
+    mut accept: F
+) -> Option<Algo> {
+    for _ in 0..max_attempts {
+        let res = scramble_with_rng(moves, rng);
+        if accept(&res.state()) {
+            return Some(res);
+        }
+    }
+    None
+}
+
+/// Check if a state is at least the given number of moves from solved.
+///
+/// The heuristic's lower bound is tried first, and a search for a solution
+/// of fewer than min moves is only used when the lower bound is too small.
+pub fn has_min_distance<H: Heuristic + ?Sized>(state: &State, heuristic: &H, min: u8) -> bool {
+    let lower_bound = heuristic.lower_bound(state, LockProj::project(state));
+    lower_bound >= min ||
+        solve(state, heuristic, MoveSet::all(), Metric::FaceTurn, min - 1).is_none()
+}
+
+/// Produce a scramble for a uniformly random reachable state.
+///
+/// A random position of the underlying cube is sampled, ignoring the locks.
+/// Positions that the solver cannot solve are rejected, and the scramble is
+/// the inverse of the solver's solution for the remaining position.
+/// Multi-step solutions are long, so the scramble is shortened with
+/// optimize(), but it is still much longer than the position's distance
+/// from solved.
+/// The inverse solution is itself legal, see Algo::inverse().
+///
+/// Positions on which the solver fails are also rejected, so the
+/// distribution is only uniform if the solver can solve every reachable
+/// state.
+/// Returns None if all of the first max_attempts positions were rejected.
+pub fn random_state_scramble<R: Rng>(
+    solver: &MultiStep,
+    rng: &mut R,
+    max_attempts: usize
+) -> Option<Algo> {
+    for _ in 0..max_attempts {
+        let state = random_position(rng);
+        if is_trapped(&state) {
             continue;
         }
-        m.apply(state);
-        history.push(m);
-        if scramble_search(state, moves - 1, history, next_gen) {
-            return true;
+        if let Ok((solution, _)) = solver.solve(&state) {
+            return Some(optimize(&State::default(), &solution.inverse(), OPTIMIZE_WINDOW));
+        }
+    }
+    None
+}
+
+/// Sample a random position of the underlying cube, ignoring the locks.
+///
+/// The random walk is long enough that the position is very close to
+/// uniformly distributed.
+fn random_position<R: Rng>(rng: &mut R) -> State {
+    let mut state = State::default();
+    for _ in 0..RANDOM_WALK_LENGTH {
+        ALL_MOVES[random_index(rng, ALL_MOVES.len())].apply(&mut state);
+    }
+    state
+}
+
+/// Check if a state is trapped in a small group of states (e.g. with every
+/// face locked) which cannot reach the solved state.
+///
+/// This is only a quick filter which saves running the solver on hopeless
+/// states.
+/// A state that can reach COMPONENT_LIMIT other states is not considered
+/// trapped, even though it may still be unsolvable.
+fn is_trapped(state: &State) -> bool {
+    let mut seen = HashSet::new();
+    let mut pending = vec![state.clone()];
+    seen.insert(state.clone());
+    while let Some(s) = pending.pop() {
+        if s.is_solved() || seen.len() >= COMPONENT_LIMIT {
+            return false;
+        }
+        for m in ALL_MOVES.iter() {
+            if s.is_locked(m.face) {
+                continue;
+            }
+            let mut next = s.clone();
+            m.apply(&mut next);
+            if seen.insert(next.clone()) {
+                pending.push(next);
+            }
+        }
+    }
+    true
+}
+
+/// Pick a random index below len.
+///
+/// Unlike generating a usize, this gives the same results on 32-bit and
+/// 64-bit platforms.
+fn random_index<R: Rng>(rng: &mut R, len: usize) -> usize {
+    rng.next_u32() as usize % len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heuristic::NopHeuristic;
+
+    /// Test that seeded scrambles are reproducible.
+    #[test]
+    fn seeded_scrambles() {
+        let actual = scramble_with_rng(25, &mut seeded_rng(1337));
+        assert_eq!(actual.0.len(), 25);
+        assert_eq!(actual, scramble_with_rng(25, &mut seeded_rng(1337)));
+        assert!(actual != scramble_with_rng(25, &mut seeded_rng(1338)));
+
+        let mut rng = seeded_rng(1337);
+        let first = scramble_with_rng(25, &mut rng);
+        assert_eq!(first, actual);
+        assert!(scramble_with_rng(25, &mut rng) != first);
+    }
+
+    /// Test that very long scrambles only turn unlocked faces.
+    #[test]
+    fn long_scrambles() {
+        let res = scramble_with_rng(5000, &mut seeded_rng(1337));
+        assert_eq!(res.0.len(), 5000);
+        let mut state = State::default();
+        for (i, m) in res.0.iter().enumerate() {
+            assert!(!state.is_locked(m.face));
+            if i > 0 {
+                assert!(res.0[i - 1].face != m.face);
+            }
+            m.apply(&mut state);
         }
-        history.pop();
-        m.inverse().apply(state);
     }
-    false
+
+    /// Test that scrambles from states with many locked faces never get
+    /// stuck, and that scrambles can end with five faces locked.
+    #[test]
+    fn locked_scrambles() {
+        let mut rng = seeded_rng(1337);
+        for _ in 0..10 {
+            let start = filtered_scramble(20, &mut rng, 1000, |s| s.num_locked() == 4).unwrap();
+            let res = extend_scramble(&start.state(), 2000, &mut rng);
+            assert_eq!(res.0.len(), 2000);
+            let mut state = start.state();
+            assert!(res.apply_legal(&mut state).is_ok());
+        }
+        assert!(filtered_scramble(20, &mut rng, 1000, |s| s.num_locked() == 5).is_some());
+    }
+
+    /// Test that from every reachable state with at most four faces locked,
+    /// whichever face was turned last, another face can be turned without
+    /// locking five faces.
+    ///
+    /// This explores about two million patterns of locks, so it is ignored by
+    /// default. Run it with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn no_dead_ends() {
+        let mut seen = HashSet::new();
+        seen.insert(LockProj::project(&State::default()));
+        let mut queue = vec![State::default()];
+        while let Some(state) = queue.pop() {
+            let mut options = Vec::new();
+            for m in ALL_MOVES.iter().filter(|m| !state.is_locked(m.face)) {
+                let mut next = state.clone();
+                m.apply(&mut next);
+                options.push((m.face, next.num_locked()));
+                if next.num_locked() <= 4 && seen.insert(LockProj::project(&next)) {
+                    queue.push(next);
+                }
+            }
+            for &(last_face, _) in &options {
+                assert!(options.iter().any(|&(face, locked)| face != last_face && locked <= 4));
+            }
+        }
+    }
+
+    /// Test filtering scrambles by difficulty.
+    #[test]
+    fn filtered_scrambles() {
+        let state = "B D2 B' U2 L2".parse::<Algo>().unwrap().state();
+        assert!(has_min_distance(&state, &NopHeuristic(), 5));
+        assert!(!has_min_distance(&state, &NopHeuristic(), 6));
+
+        let res = filtered_scramble(10, &mut seeded_rng(1337), 1000, |s| s.num_locked() >= 3)
+            .unwrap();
+        assert_eq!(res.0.len(), 10);
+        assert!(res.state().num_locked() >= 3);
+        assert_eq!(filtered_scramble(10, &mut seeded_rng(1337), 100, |s| s.num_locked() > 6),
+            None);
+    }
+
+    /// Test that random-state scrambles give up when every position is
+    /// rejected.
+    #[test]
+    fn random_state_attempts() {
+        let solver = MultiStep::new(Vec::new());
+        assert_eq!(random_state_scramble(&solver, &mut seeded_rng(1337), 10), None);
+    }
 }
@@ -5,21 +5,23 @@ use std::sync::mpsc::channel;
 
 use super::heuristic::Heuristic;
 use super::move_gen::MoveGen;
-use super::moves::{Algo, Move};
+use super::moves::{Algo, Metric, Move, MoveSet};
 use super::proj::{LockProj, Proj};
 use super::state::State;
 use super::thread::ThreadScope;
 
 macro_rules! parallel_search {
-    ( $state:expr, $heuristic:expr, $depth:expr, $serial_fn:expr, $search_fn:expr ) => {
+    ( $state:expr, $heuristic:expr, $moves:expr, $metric:expr, $depth:expr, $serial_fn:expr,
+      $search_fn:expr ) => {
         if $depth == 0 {
-            $serial_fn($state, $heuristic, $depth)
+            $serial_fn($state, $heuristic, $moves, $metric, $depth)
         } else {
             let (send, recv) = channel();
 
             let mut threads = Vec::new();
-            for (gen, m) in MoveGen::new() {
-                if $state.is_locked(m.face) {
+            for (i, (gen, m)) in MoveGen::restricted($moves).into_iter().enumerate() {
+                let cost = $metric.cost(m);
+                if $state.is_locked(m.face) || cost > $depth {
                     continue;
                 }
                 let local_send = send.clone();
@@ -27,22 +29,21 @@ macro_rules! parallel_search {
                     let mut local_state = $state.clone();
                     m.apply(&mut local_state);
                     let mut hist = vec![m];
-                    if $search_fn(&local_state, $heuristic, $depth - 1, &mut hist, gen) {
-                        local_send.send(hist).unwrap();
+                    if $search_fn(&local_state, $heuristic, $metric, $depth - cost, &mut hist,
+                                  gen) {
+                        local_send.send((i, hist)).unwrap();
                     }
                 }));
             }
 
             drop(send);
 
-            let mut best_solution: Option<Vec<Move>> = None;
-            for solution in recv {
-                if best_solution.is_none() ||
-                    solution.len() < best_solution.as_ref().unwrap().len() {
-                    best_solution = Some(solution);
-                }
-            }
-            best_solution.map(Algo)
+            // Break ties by the order of the first move, so that the result
+            // does not depend on thread scheduling.
+            recv.into_iter()
+                .map(|(i, hist)| (Algo(hist), i))
+                .min_by_key(|&(ref solution, i)| (solution.cost($metric), i))
+                .map(|(solution, _)| solution)
         }
     }
 }
@@ -51,15 +52,27 @@ macro_rules! parallel_search {
 ///
 /// Uses multiple threads for the search.
 ///
+/// Only moves from the given move set are used, so the heuristic should be
+/// generated with the same (or a larger) move set.
+///
+/// The depth is measured in the given metric, and the heuristic should be
+/// generated with the same metric.
+///
 /// This may find sub-optimal solutions if the given depth is too large.
 /// Thus, it is recommended that callers iteratively try deeper and deeper
 /// searches until a solution is found.
 pub fn solve<H: Heuristic + ?Sized>(
     state: &State,
     heuristic: &H,
+    moves: MoveSet,
+    metric: Metric,
     depth: u8
 ) -> Option<Algo> {
-    parallel_search!(state, heuristic, depth, solve_serial, solve_search)
+    let search = |a: &State, b: &H, c: Metric, d: u8, e: &mut Vec<Move>, f: MoveGen| {
+        solve_search(a, b, c, d, e, f, &mut |_: &[Move]| true)
+    };
+    let search_ref = &search;
+    parallel_search!(state, heuristic, moves, metric, depth, solve_serial, search_ref)
 }
 
 /// Find a solution of the given depth.
@@ -70,10 +83,61 @@ pub fn solve<H: Heuristic + ?Sized>(
 pub fn solve_serial<H: Heuristic + ?Sized>(
     state: &State,
     heuristic: &H,
+    moves: MoveSet,
+    metric: Metric,
     depth: u8
 ) -> Option<Algo> {
     let mut solution = Vec::new();
-    if solve_search(state, heuristic, depth, &mut solution, MoveGen::new()) {
+    let gen = MoveGen::restricted(moves);
+    if solve_search(state, heuristic, metric, depth, &mut solution, gen, &mut |_| true) {
+        Some(Algo(solution))
+    } else {
+        None
+    }
+}
+
+/// Find every solution whose length is at most the given depth.
+///
+/// Uses a single thread.
+///
+/// Unlike solve(), this keeps searching after the first solution is found,
+/// so it can be used to pick between several near-optimal solutions.
+/// Solutions never pass through the solved state, and only one ordering of
+/// commuting moves (e.g. U D and D U) is produced.
+pub fn solve_all<H: Heuristic + ?Sized>(
+    state: &State,
+    heuristic: &H,
+    moves: MoveSet,
+    metric: Metric,
+    depth: u8
+) -> Vec<Algo> {
+    let mut solutions = Vec::new();
+    let mut history = Vec::new();
+    let gen = MoveGen::restricted(moves);
+    solve_search(state, heuristic, metric, depth, &mut history, gen, &mut |solution| {
+        solutions.push(Algo(solution.to_vec()));
+        false
+    });
+    solutions
+}
+
+/// Find a sequence of moves of the given depth that takes one state to
+/// another.
+///
+/// Uses a single thread, and no heuristic, so this is only practical for
+/// small depths.
+///
+/// Like solve(), this may find sub-optimal solutions if the given depth is
+/// too large.
+pub fn solve_between(
+    start: &State,
+    goal: &State,
+    moves: MoveSet,
+    metric: Metric,
+    depth: u8
+) -> Option<Algo> {
+    let mut solution = Vec::new();
+    if goal_search(start, goal, metric, depth, &mut solution, MoveGen::restricted(moves)) {
         Some(Algo(solution))
     } else {
         None
@@ -84,21 +148,27 @@ pub fn solve_serial<H: Heuristic + ?Sized>(
 ///
 /// Uses multiple threads for the search.
 ///
+/// Only moves from the given move set are used, and the depth is measured in
+/// the given metric.
+///
 /// This may find sub-optimal solutions if the given depth is too large.
 /// Thus, it is recommended that callers iteratively try deeper and deeper
 /// searches until a solution is found.
 pub fn proj_solve<P: Proj, H: Heuristic + ?Sized>(
     state: &State,
     heuristic: &H,
+    moves: MoveSet,
+    metric: Metric,
     depth: u8
 ) -> Option<Algo> {
     // TODO: why does this blow up without argument types?
-    let search = |a: &State, b: &H, c: u8, d: &mut Vec<Move>, e: MoveGen| {
-        let solved_state = P::project(&State::default());
-        proj_solve_search::<P, H>(&solved_state, a, b, c, d, e)
+    let search = |a: &State, b: &H, c: Metric, d: u8, e: &mut Vec<Move>, f: MoveGen| {
+        let goal = ProjGoal::<P, H>::new(b, c, false);
+        proj_solve_search(&goal, a, d, e, f, &mut |_: &[Move]| true)
     };
     let search_ref = &search;
-    parallel_search!(state, heuristic, depth, proj_solve_serial::<P, H>, search_ref)
+    parallel_search!(state, heuristic, moves, metric, depth, proj_solve_serial::<P, H>,
+                     search_ref)
 }
 
 /// Find a solution under a projection of the given depth.
@@ -109,16 +179,18 @@ pub fn proj_solve<P: Proj, H: Heuristic + ?Sized>(
 pub fn proj_solve_serial<P: Proj, H: Heuristic + ?Sized>(
     state: &State,
     heuristic: &H,
+    moves: MoveSet,
+    metric: Metric,
     depth: u8
 ) -> Option<Algo> {
     let mut solution = Vec::new();
-    let success = proj_solve_search::<P, H>(
-        &P::project(&State::default()),
+    let success = proj_solve_search(
+        &ProjGoal::<P, H>::new(heuristic, metric, false),
         state,
-        heuristic,
         depth,
         &mut solution,
-        MoveGen::new()
+        MoveGen::restricted(moves),
+        &mut |_| true
     );
     if success {
         Some(Algo(solution))
@@ -127,10 +199,76 @@ pub fn proj_solve_serial<P: Proj, H: Heuristic + ?Sized>(
     }
 }
 
+/// Find solutions under a projection whose length is at most the given
+/// depth.
+///
+/// Uses a single thread.
+///
+/// Projections tend to have many more solutions than the full puzzle, so the
+/// search stops once limit solutions have been found.
+/// See solve_all() for details.
+pub fn proj_solve_all<P: Proj, H: Heuristic + ?Sized>(
+    state: &State,
+    heuristic: &H,
+    moves: MoveSet,
+    metric: Metric,
+    depth: u8,
+    limit: usize
+) -> Vec<Algo> {
+    let mut solutions = Vec::new();
+    if limit == 0 {
+        return solutions;
+    }
+    proj_solve_each::<P, H, _>(state, heuristic, moves, metric, depth, |solution| {
+        solutions.push(Algo(solution.to_vec()));
+        solutions.len() == limit
+    });
+    solutions
+}
+
+/// Call a function on every solution under a projection whose length is at
+/// most the given depth, until the function returns true.
+///
+/// Uses a single thread.
+pub(crate) fn proj_solve_each<P: Proj, H: Heuristic + ?Sized, F: FnMut(&[Move]) -> bool>(
+    state: &State,
+    heuristic: &H,
+    moves: MoveSet,
+    metric: Metric,
+    depth: u8,
+    mut f: F
+) {
+    let mut history = Vec::new();
+    let goal = ProjGoal::<P, H>::new(heuristic, metric, false);
+    proj_solve_search(&goal, state, depth, &mut history, MoveGen::restricted(moves), &mut f);
+}
+
+/// Call a function on every solution under a projection whose cost is
+/// exactly the given depth, until the function returns true.
+///
+/// Sequences that pass through a solved projection before the end are not
+/// solutions, since a search of a lower depth finds them already.
+/// This lets callers try deeper and deeper searches without seeing any
+/// solution twice.
+///
+/// Uses a single thread.
+pub(crate) fn proj_solve_exact<P: Proj, H: Heuristic + ?Sized, F: FnMut(&[Move]) -> bool>(
+    state: &State,
+    heuristic: &H,
+    moves: MoveSet,
+    metric: Metric,
+    depth: u8,
+    mut f: F
+) {
+    let mut history = Vec::new();
+    let goal = ProjGoal::<P, H>::new(heuristic, metric, true);
+    proj_solve_search(&goal, state, depth, &mut history, MoveGen::restricted(moves), &mut f);
+}
+
 macro_rules! search_step {
-    ( $state:expr, $history:expr, $m:expr ) => {
+    ( $state:expr, $history:expr, $m:expr, $cost:expr, $depth:expr ) => {
         {
-            if $state.is_locked($m.face) {
+            if $state.is_locked($m.face) || $cost > $depth {
                 continue;
             }
             let mut new_state = $state.clone();
@@ -141,21 +279,27 @@ macro_rules! search_step {
     }
 }
 
-fn solve_search<H: Heuristic + ?Sized>(
+/// Search for solutions, calling `found` on each one.
+///
+/// The search stops as soon as `found` returns true.
+fn solve_search<H: Heuristic + ?Sized, F: FnMut(&[Move]) -> bool>(
     state: &State,
     heuristic: &H,
+    metric: Metric,
     depth: u8,
     history: &mut Vec<Move>,
-    gen: MoveGen
+    gen: MoveGen,
+    found: &mut F
 ) -> bool {
     if state.is_solved() {
-        return true;
+        return found(history);
     } else if depth == 0 || depth < heuristic.lower_bound(state, Proj::project(state)) {
         return false;
     }
     for (new_gen, m) in gen {
-        let new_state = search_step!(state, history, m);
-        if solve_search(&new_state, heuristic, depth - 1, history, new_gen) {
+        let cost = metric.cost(m);
+        let new_state = search_step!(state, history, m, cost, depth);
+        if solve_search(&new_state, heuristic, metric, depth - cost, history, new_gen, found) {
             return true;
         }
         history.pop();
@@ -163,24 +307,74 @@ fn solve_search<H: Heuristic + ?Sized>(
     false
 }
 
-fn proj_solve_search<P: Proj, H: Heuristic + ?Sized>(
-    solution: &P,
+/// Search for a sequence of moves that reaches the goal state.
+fn goal_search(
     state: &State,
-    heuristic: &H,
+    goal: &State,
+    metric: Metric,
     depth: u8,
     history: &mut Vec<Move>,
     gen: MoveGen
+) -> bool {
+    if state == goal {
+        return true;
+    } else if depth == 0 {
+        return false;
+    }
+    for (new_gen, m) in gen {
+        let cost = metric.cost(m);
+        let new_state = search_step!(state, history, m, cost, depth);
+        if goal_search(&new_state, goal, metric, depth - cost, history, new_gen) {
+            return true;
+        }
+        history.pop();
+    }
+    false
+}
+
+/// The parts of a search under a projection that are the same at every node.
+struct ProjGoal<'a, P: Proj, H: Heuristic + ?Sized + 'a> {
+    solution: P,
+    heuristic: &'a H,
+    metric: Metric,
+    exact: bool
+}
+
+impl<'a, P: Proj, H: Heuristic + ?Sized> ProjGoal<'a, P, H> {
+    /// Create a goal for a search, where exact only accepts solutions that
+    /// use up the whole depth.
+    fn new(heuristic: &'a H, metric: Metric, exact: bool) -> Self {
+        ProjGoal{
+            solution: P::project(&State::default()),
+            heuristic: heuristic,
+            metric: metric,
+            exact: exact
+        }
+    }
+}
+
+/// Search for solutions under a projection, calling `found` on each one.
+///
+/// The search stops as soon as `found` returns true.
+fn proj_solve_search<P: Proj, H: Heuristic + ?Sized, F: FnMut(&[Move]) -> bool>(
+    goal: &ProjGoal<P, H>,
+    state: &State,
+    depth: u8,
+    history: &mut Vec<Move>,
+    gen: MoveGen,
+    found: &mut F
 ) -> bool {
     let lock_proj = LockProj::project(state);
     let projection = Proj::project_with_lock(state, lock_proj.clone());
-    if solution == &projection {
-        return true;
-    } else if depth == 0 || depth < heuristic.lower_bound(state, lock_proj) {
+    if goal.solution == projection {
+        return (depth == 0 || !goal.exact) && found(history);
+    } else if depth == 0 || depth < goal.heuristic.lower_bound(state, lock_proj) {
         return false;
     }
     for (new_gen, m) in gen {
-        let new_state = search_step!(state, history, m);
-        if proj_solve_search(solution, &new_state, heuristic, depth - 1, history, new_gen) {
+        let cost = goal.metric.cost(m);
+        let new_state = search_step!(state, history, m, cost, depth);
+        if proj_solve_search(goal, &new_state, depth - cost, history, new_gen, found) {
             return true;
         }
         history.pop();
@@ -191,14 +385,15 @@ fn proj_solve_search<P: Proj, H: Heuristic + ?Sized>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use heuristic::NopHeuristic;
-    use moves::Algo;
-    use proj::LockProj;
+    use heuristic::{NopHeuristic, ProjHeuristic};
+    use moves::{Algo, Metric, MoveSet};
+    use proj::{CornerProj, LockProj};
 
     /// Test solving zero-move scrambles.
     #[test]
     fn zero_move_scramble() {
-        let actual = solve(&State::default(), &NopHeuristic(), 0).unwrap();
+        let actual = solve(&State::default(), &NopHeuristic(), MoveSet::all(), Metric::FaceTurn,
+            0).unwrap();
         assert_eq!(actual, Algo(Vec::new()));
     }
 
@@ -206,7 +401,8 @@ mod tests {
     #[test]
     fn one_move_scramble() {
         let algo: Algo = "L'".parse().unwrap();
-        let actual = solve(&algo.state(), &NopHeuristic(), 1).unwrap();
+        let actual = solve(&algo.state(), &NopHeuristic(), MoveSet::all(), Metric::FaceTurn,
+            1).unwrap();
         assert_eq!(actual, "L".parse().unwrap());
     }
 
@@ -214,7 +410,7 @@ mod tests {
     #[test]
     fn not_enough_depth() {
         let algo: Algo = "B D2 B' U2 L2".parse().unwrap();
-        let actual = solve(&algo.state(), &NopHeuristic(), 4);
+        let actual = solve(&algo.state(), &NopHeuristic(), MoveSet::all(), Metric::FaceTurn, 4);
         assert!(actual.is_none());
     }
 
@@ -222,24 +418,88 @@ mod tests {
     #[test]
     fn five_move_scramble() {
         let algo: Algo = "B D2 B' U2 L2".parse().unwrap();
-        let actual = solve(&algo.state(), &NopHeuristic(), 5).unwrap();
+        let actual = solve(&algo.state(), &NopHeuristic(), MoveSet::all(), Metric::FaceTurn,
+            5).unwrap();
         assert_eq!(actual, "L2 U2 B D2 B'".parse().unwrap());
     }
 
+    /// Test solving a scramble in the quarter-turn metric.
+    #[test]
+    fn quarter_turn_scramble() {
+        let algo: Algo = "B D2 B' U2 L2".parse().unwrap();
+        let state = algo.state();
+        let metric = Metric::QuarterTurn;
+        assert!(solve(&state, &NopHeuristic(), MoveSet::all(), metric, 7).is_none());
+        let actual = solve(&state, &NopHeuristic(), MoveSet::all(), metric, 8).unwrap();
+        assert_eq!(actual.cost(metric), 8);
+        assert_eq!(actual, "L2 U2 B D2 B'".parse().unwrap());
+    }
+
+    /// Test enumerating all the solutions to a scramble.
+    #[test]
+    fn all_solutions() {
+        let algo: Algo = "R2 U2".parse().unwrap();
+        let state = algo.state();
+        let metric = Metric::FaceTurn;
+        assert!(solve_all(&state, &NopHeuristic(), MoveSet::all(), metric, 1).is_empty());
+        let optimal = solve_all(&state, &NopHeuristic(), MoveSet::all(), metric, 5);
+        assert_eq!(optimal, vec!["U2 R2".parse().unwrap()]);
+        let longer = solve_all(&state, &NopHeuristic(), MoveSet::all(), metric, 6);
+        assert_eq!(longer, vec!["U2 R2".parse().unwrap(), "D2 R2 L2 U2 D2 L2".parse().unwrap()]);
+    }
+
     /// Test solving the arrows on a five-move scramble.
     #[test]
     fn proj_five_move_scramble() {
         let algo: Algo = "B D2 B' U2 L2".parse().unwrap();
-        let actual = proj_solve::<LockProj, _>(&algo.state(), &NopHeuristic(), 5).unwrap();
+        let actual = proj_solve::<LockProj, _>(&algo.state(), &NopHeuristic(), MoveSet::all(),
+            Metric::FaceTurn, 5).unwrap();
         assert_eq!(actual, "L2 U2 B D2 B'".parse().unwrap());
     }
 
+    /// Test enumerating solutions under a projection.
+    #[test]
+    fn proj_all_solutions() {
+        let algo: Algo = "R U R' U' R' F R2 U' R' U' R U R' F'".parse().unwrap();
+        let state = algo.state();
+        let metric = Metric::FaceTurn;
+        let actual = proj_solve_all::<LockProj, _>(&state, &NopHeuristic(), MoveSet::all(),
+            metric, 1, 10);
+        assert_eq!(actual, vec!["U2".parse().unwrap()]);
+        let longer = proj_solve_all::<LockProj, _>(&state, &NopHeuristic(), MoveSet::all(),
+            metric, 5, 100);
+        assert_eq!(longer.len(), 18);
+        let limited = proj_solve_all::<LockProj, _>(&state, &NopHeuristic(), MoveSet::all(),
+            metric, 5, 3);
+        assert_eq!(limited.len(), 3);
+    }
+
     /// Test a case when it's easier to solve a projection than the whole cube.
     #[test]
     fn proj_simpler_solution() {
         // Apply a T-perm, which just swaps the top right and top left edges.
         let algo: Algo = "R U R' U' R' F R2 U' R' U' R U R' F'".parse().unwrap();
-        let actual = proj_solve_serial::<LockProj, _>(&algo.state(), &NopHeuristic(), 1).unwrap();
+        let actual = proj_solve_serial::<LockProj, _>(&algo.state(), &NopHeuristic(),
+            MoveSet::all(), Metric::FaceTurn, 1).unwrap();
         assert_eq!(actual, "U2".parse().unwrap());
     }
+
+    /// Test solving scrambles with restricted move sets.
+    #[test]
+    fn restricted_scramble() {
+        let metric = Metric::FaceTurn;
+
+        let algo: Algo = "U' R".parse().unwrap();
+        let moves: MoveSet = "R,U".parse().unwrap();
+        let heuristic = ProjHeuristic::<CornerProj>::generate(3, moves, metric);
+        let actual = solve(&algo.state(), &heuristic, moves, metric, 2).unwrap();
+        assert_eq!(actual, "R' U".parse().unwrap());
+        let moves: MoveSet = "U,F".parse().unwrap();
+        assert!(solve(&algo.state(), &NopHeuristic(), moves, metric, 2).is_none());
+
+        let algo: Algo = "R2 U2".parse().unwrap();
+        let actual = solve(&algo.state(), &NopHeuristic(), MoveSet::half_turns(), metric,
+            2).unwrap();
+        assert_eq!(actual, "U2 R2".parse().unwrap());
+    }
 }
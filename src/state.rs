@@ -1,8 +1,13 @@
 //! Definition of the puzzle state.
 
+use std::error::Error;
 use std::fmt;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// The faces in the order that they are stored in a State.
+const FACES: [Face; 6] = [Face::U, Face::D, Face::F, Face::B, Face::R, Face::L];
 
 /// The sticker configuration of a puzzle.
 ///
@@ -102,6 +107,40 @@ impl State {
         }
         false
     }
+
+    /// Count the faces that are locked.
+    pub fn num_locked(&self) -> usize {
+        FACES.iter().filter(|f| self.is_locked(**f)).count()
+    }
+
+    /// Get the faces that are locked, in the order U, D, F, B, R, L.
+    pub fn locked_faces(&self) -> Vec<Face> {
+        FACES.iter().cloned().filter(|&f| self.is_locked(f)).collect()
+    }
+
+    /// Encode the state as a single line of facelets.
+    ///
+    /// Each face is written as 9 characters, including the center, in the
+    /// order U, D, F, B, R, L.
+    /// A face is read from the top left to the bottom right, as described
+    /// for State, and a '^' follows every sticker with an arrow.
+    /// For example, the U face of a solved puzzle is `UU^UUUUUU^U`.
+    pub fn to_facelet_string(&self) -> String {
+        let mut res = String::new();
+        for face in FACES.iter() {
+            let stickers = self.face(*face);
+            for (i, sticker) in stickers.iter().enumerate() {
+                if i == 4 {
+                    res.push_str(&format!("{}", face));
+                }
+                res.push_str(&format!("{}", sticker.face));
+                if sticker.direction != Direction::Neutral {
+                    res.push('^');
+                }
+            }
+        }
+        res
+    }
 }
 
 impl Hash for State {
@@ -138,6 +177,12 @@ impl PartialEq for State {
 impl Eq for State {
 }
 
+impl Debug for State {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        Display::fmt(self, f)
+    }
+}
+
 impl Display for State {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         use Face::*;
@@ -162,6 +207,171 @@ impl Display for State {
     }
 }
 
+impl FromStr for State {
+    type Err = ParseStateError;
+
+    /// Parse a state in the format of to_facelet_string() or Display.
+    ///
+    /// For facelet strings, whitespace is ignored, so faces may be separated
+    /// by spaces.
+    fn from_str(s: &str) -> Result<State, ParseStateError> {
+        if s.trim().starts_with('[') {
+            return parse_display(s.trim());
+        }
+        let stickers = parse_facelets(s)?;
+        if stickers.len() != 54 {
+            return Err(ParseStateError::new(&format!("expected 54 stickers but got {}",
+                stickers.len())));
+        }
+        let mut res = State::default();
+        for (face, row) in FACES.iter().zip(stickers.chunks(9)) {
+            res.set_face_row(*face, row)?;
+        }
+        res.validate()?;
+        Ok(res)
+    }
+}
+
+/// Parse the stickers of a facelet string, including the centers.
+fn parse_facelets(s: &str) -> Result<Vec<Sticker>, ParseStateError> {
+    let mut stickers = Vec::new();
+    let mut after_arrow = false;
+    for ch in s.chars().filter(|c| !c.is_whitespace()) {
+        if ch == '^' {
+            let idx = stickers.len();
+            if after_arrow || idx % 9 != 2 && idx % 9 != 4 && idx % 9 != 6 && idx % 9 != 8 {
+                return Err(ParseStateError::new("'^' must follow an edge sticker"));
+            }
+            let sticker: &mut Sticker = &mut stickers[idx - 1];
+            sticker.direction = sticker.face.standard_direction();
+            after_arrow = true;
+        } else {
+            after_arrow = false;
+            let face = Face::from_char(ch).ok_or_else(|| {
+                ParseStateError::new(&format!("invalid character: {}", ch))
+            })?;
+            stickers.push(Sticker{face: face, direction: Direction::Neutral});
+        }
+    }
+    Ok(stickers)
+}
+
+/// Parse a state in the format produced by Display.
+fn parse_display(s: &str) -> Result<State, ParseStateError> {
+    if !s.ends_with(']') {
+        return Err(ParseStateError::new("missing closing ']'"));
+    }
+    let faces: Vec<&str> = s[1..s.len() - 1].split(',').collect();
+    if faces.len() != 6 {
+        return Err(ParseStateError::new(&format!("expected 6 faces but got {}", faces.len())));
+    }
+    let mut res = State::default();
+    for (face, face_str) in FACES.iter().zip(faces) {
+        let tokens: Vec<&str> = face_str.split_whitespace().collect();
+        if tokens.len() != 8 {
+            return Err(ParseStateError::new(&format!("expected 8 stickers on {} face but got {}",
+                face, tokens.len())));
+        }
+        for (i, (dst, token)) in res.face_mut(*face).iter_mut().zip(tokens).enumerate() {
+            *dst = parse_sticker(token)?;
+            if dst.direction != Direction::Neutral && i != 1 && i != 3 && i != 4 && i != 6 {
+                return Err(ParseStateError::new(&format!("arrow on a corner sticker: {}",
+                    token)));
+            }
+        }
+    }
+    res.validate()?;
+    Ok(res)
+}
+
+/// Parse a sticker such as "U", "Uc" or "Uc'".
+fn parse_sticker(token: &str) -> Result<Sticker, ParseStateError> {
+    let invalid = || ParseStateError::new(&format!("invalid sticker: {}", token));
+    let face = token.chars().next().and_then(Face::from_char).ok_or_else(&invalid)?;
+    let direction = match &token[1..] {
+        "" => Direction::Neutral,
+        "c" => Direction::Clockwise,
+        "c'" => Direction::Counter,
+        _ => return Err(invalid())
+    };
+    if direction != Direction::Neutral && direction != face.standard_direction() {
+        return Err(ParseStateError::new(&format!("wrong arrow direction: {}", token)));
+    }
+    Ok(Sticker{face: face, direction: direction})
+}
+
+impl State {
+    /// Replace one face with 9 stickers in the format of to_facelet_string(),
+    /// such as "UU^UUUUUU^U".
+    ///
+    /// This only checks the face itself; use validate() once every face has
+    /// been set.
+    pub fn set_face_str(&mut self, face: Face, s: &str) -> Result<(), ParseStateError> {
+        let stickers = parse_facelets(s)?;
+        if stickers.len() != 9 {
+            return Err(ParseStateError::new(&format!("expected 9 stickers on {} face but got {}",
+                face, stickers.len())));
+        }
+        self.set_face_row(face, &stickers)
+    }
+
+    /// Replace one face with a row of 9 stickers, including the center.
+    fn set_face_row(&mut self, face: Face, row: &[Sticker]) -> Result<(), ParseStateError> {
+        if row[4].face != face {
+            return Err(ParseStateError::new(&format!("invalid center for {} face", face)));
+        }
+        let dst = self.face_mut(face);
+        dst[0..4].copy_from_slice(&row[0..4]);
+        dst[4..8].copy_from_slice(&row[5..9]);
+        Ok(())
+    }
+
+    /// Check that every color appears on 8 stickers, 2 of which have
+    /// arrows.
+    ///
+    /// Parsing a whole state does this automatically.
+    pub fn validate(&self) -> Result<(), ParseStateError> {
+        for face in FACES.iter() {
+            let count = self.0.iter().filter(|s| s.face == *face).count();
+            let arrows = self.0.iter()
+                .filter(|s| s.face == *face && s.direction != Direction::Neutral)
+                .count();
+            if count != 8 {
+                return Err(ParseStateError::new(&format!("expected 8 {} stickers but got {}",
+                    face, count)));
+            } else if arrows != 2 {
+                return Err(ParseStateError::new(&format!("expected 2 {} arrows but got {}",
+                    face, arrows)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error from parsing a state.
+#[derive(Clone, Debug)]
+pub struct ParseStateError {
+    message: String
+}
+
+impl ParseStateError {
+    fn new(message: &str) -> ParseStateError {
+        ParseStateError{message: message.to_owned()}
+    }
+}
+
+impl Display for ParseStateError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ParseStateError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
 /// A sticker on the puzzle.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Sticker {
@@ -220,6 +430,22 @@ impl Face {
     }
 }
 
+impl Face {
+    /// Parse a face from its letter.
+    pub fn from_char(ch: char) -> Option<Face> {
+        use Face::*;
+        match ch {
+            'U' => Some(U),
+            'D' => Some(D),
+            'F' => Some(F),
+            'B' => Some(B),
+            'R' => Some(R),
+            'L' => Some(L),
+            _ => None
+        }
+    }
+}
+
 impl Display for Face {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         use Face::*;
@@ -237,6 +463,7 @@ impl Display for Face {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use moves::Algo;
 
     /// Test the Display output for a solved puzzle.
     #[test]
@@ -246,4 +473,50 @@ mod tests {
             "B B B Bc' Bc' B B B, R Rc R R R R Rc R, L Lc' L L L L Lc' L]";
         assert_eq!(actual, expected);
     }
+
+    /// Test encoding and decoding facelet strings.
+    #[test]
+    fn facelet_round_trip() {
+        let solved = State::default().to_facelet_string();
+        assert_eq!(solved, "UU^UUUUUU^UDD^DDDDDD^DFFFF^FF^FFFBBBB^BB^BBBRR^RRRRRR^RLL^LLLLLL^L");
+        assert_eq!(solved.parse::<State>().unwrap(), State::default());
+
+        let state = "B D2 B' U2 L2 R F' D".parse::<Algo>().unwrap().state();
+        let encoded = state.to_facelet_string();
+        assert_eq!(encoded.parse::<State>().unwrap(), state);
+        let spaced = encoded.replace("D", " D");
+        assert_eq!(spaced.parse::<State>().unwrap(), state);
+
+        assert!("UU^UUUUUU^U".parse::<State>().is_err());
+        assert!(solved.replacen("U^", "U^^", 1).parse::<State>().is_err());
+        assert!(solved.replacen("U^", "^U", 1).parse::<State>().is_err());
+        assert!(solved.replacen("UU^", "UU", 1).parse::<State>().is_err());
+        assert!(solved.replacen("DD^", "UD^", 1).parse::<State>().is_err());
+
+        let mut state = State::default();
+        assert!(state.set_face_str(Face::U, "UU^UUUUUU^U").is_ok());
+        assert_eq!(state, State::default());
+        assert!(state.set_face_str(Face::U, "UU^UUUUU^U").is_err());
+        assert!(state.set_face_str(Face::U, "UU^UUUUUU^UD").is_err());
+        assert!(state.set_face_str(Face::D, "UU^UUUUUU^U").is_err());
+        assert!(state.set_face_str(Face::F, "UU^UUFUUU^U").is_ok());
+        assert!(state.validate().is_err());
+    }
+
+    /// Test parsing the Display output.
+    #[test]
+    fn display_round_trip() {
+        let state = "B D2 B' U2 L2 R F' D".parse::<Algo>().unwrap().state();
+        let displayed = format!("{}", state);
+        assert_eq!(displayed.parse::<State>().unwrap(), state);
+        assert_eq!(format!("{}", State::default()).parse::<State>().unwrap(), State::default());
+
+        assert!(displayed[..displayed.len() - 1].parse::<State>().is_err());
+        assert!(displayed.replacen(", ", " ", 1).parse::<State>().is_err());
+        assert!(displayed.replacen("c'", "c", 1).parse::<State>().is_err());
+        assert!(displayed.replacen("c'", "", 1).parse::<State>().is_err());
+        assert!(displayed.replacen("U", "X", 1).parse::<State>().is_err());
+        let solved = format!("{}", State::default());
+        assert!(solved.replacen("[U Uc' U", "[Uc' U U", 1).parse::<State>().is_err());
+    }
 }
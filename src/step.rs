@@ -0,0 +1,258 @@
+//! Individual steps of a multi-step solve.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
+
+use super::heuristic::{CancelHeuristic, Heuristic};
+use super::moves::{Algo, Metric, MoveSet};
+use super::proj::Proj;
+use super::solve::{proj_solve, proj_solve_all, solve};
+use super::state::State;
+
+/// A step of a multi-step solve, which brings the puzzle closer to solved.
+pub trait Step: Send + Sync {
+    /// Get a short description of the step, such as its goal projection.
+    fn name(&self) -> String;
+
+    /// Get the deepest search the step performs before giving up.
+    fn max_depth(&self) -> u8;
+
+    /// Find a short sequence of moves that completes the step.
+    ///
+    /// The solution may be split into several parts, which are reported
+    /// separately by the multi-step solver.
+    /// Returns None if the step could not be completed within its budget, or
+    /// if the search was cut off because cancel was set or the deadline
+    /// passed.
+    fn solve(
+        &self,
+        s: &State,
+        cancel: &AtomicBool,
+        deadline: Option<Instant>
+    ) -> Option<Vec<Algo>>;
+
+    /// Find up to k different ways to complete the step, shortest first.
+    ///
+    /// Like solve(), the search ends soon after cancel is set or the
+    /// deadline has passed, and the candidates found so far are returned.
+    /// By default, this only returns the result of solve().
+    fn candidates(
+        &self,
+        s: &State,
+        k: usize,
+        cancel: &AtomicBool,
+        deadline: Option<Instant>
+    ) -> Vec<Vec<Algo>> {
+        self.solve(s, cancel, deadline).into_iter().take(k).collect()
+    }
+}
+
+/// A step that solves the puzzle under a projection.
+pub struct ProjStep<P: Proj> {
+    name: String,
+    heuristic: Arc<Heuristic>,
+    max_depth: u8,
+    phantom: PhantomData<P>
+}
+
+impl<P: Proj> ProjStep<P> {
+    /// Create a step that searches for solutions of up to max_depth moves.
+    pub fn new(name: &str, heuristic: Arc<Heuristic>, max_depth: u8) -> ProjStep<P> {
+        ProjStep{
+            name: name.to_owned(),
+            heuristic: heuristic,
+            max_depth: max_depth,
+            phantom: PhantomData
+        }
+    }
+}
+
+impl<P: Proj> Step for ProjStep<P> {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn max_depth(&self) -> u8 {
+        self.max_depth
+    }
+
+    fn solve(
+        &self,
+        s: &State,
+        cancel: &AtomicBool,
+        deadline: Option<Instant>
+    ) -> Option<Vec<Algo>> {
+        let heuristic = CancelHeuristic::with_deadline(&*self.heuristic, cancel, deadline);
+        for i in 0..=self.max_depth {
+            let res = proj_solve::<P, _>(s, &heuristic, MoveSet::all(), Metric::FaceTurn, i);
+            if let Some(solution) = res {
+                return Some(vec![solution]);
+            } else if heuristic.is_cancelled() {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Find optimal solutions, followed by solutions one move longer if
+    /// there are fewer than k optimal ones.
+    fn candidates(
+        &self,
+        s: &State,
+        k: usize,
+        cancel: &AtomicBool,
+        deadline: Option<Instant>
+    ) -> Vec<Vec<Algo>> {
+        let mut res = match self.solve(s, cancel, deadline) {
+            Some(mut parts) => vec![parts.remove(0)],
+            None => return Vec::new()
+        };
+        let depth = res[0].0.len() as u8;
+        let heuristic = CancelHeuristic::with_deadline(&*self.heuristic, cancel, deadline);
+        let search = |depth, limit| {
+            proj_solve_all::<P, _>(s, &heuristic, MoveSet::all(), Metric::FaceTurn, depth, limit)
+        };
+        let mut more = search(depth, k);
+        if more.len() < k && depth < self.max_depth {
+            let limit = k + more.len();
+            more.extend(search(depth + 1, limit));
+        }
+        for solution in more {
+            if res.len() >= k {
+                break;
+            } else if !res.contains(&solution) {
+                res.push(solution);
+            }
+        }
+        res.into_iter().map(|solution| vec![solution]).collect()
+    }
+}
+
+/// A step that completely solves the puzzle.
+pub struct SolveStep {
+    heuristic: Arc<Heuristic>,
+    max_depth: u8,
+    moves: MoveSet
+}
+
+impl SolveStep {
+    /// Create a step that searches for solutions of up to max_depth moves.
+    pub fn new(heuristic: Arc<Heuristic>, max_depth: u8) -> SolveStep {
+        SolveStep::with_moves(heuristic, max_depth, MoveSet::all())
+    }
+
+    /// Create a step that only searches for solutions which use the given
+    /// moves.
+    ///
+    /// The heuristic should be generated for the same move set.
+    pub fn with_moves(heuristic: Arc<Heuristic>, max_depth: u8, moves: MoveSet) -> SolveStep {
+        SolveStep{
+            heuristic: heuristic,
+            max_depth: max_depth,
+            moves: moves
+        }
+    }
+}
+
+impl Step for SolveStep {
+    fn name(&self) -> String {
+        "solved".to_owned()
+    }
+
+    fn max_depth(&self) -> u8 {
+        self.max_depth
+    }
+
+    fn solve(
+        &self,
+        s: &State,
+        cancel: &AtomicBool,
+        deadline: Option<Instant>
+    ) -> Option<Vec<Algo>> {
+        let heuristic = CancelHeuristic::with_deadline(&*self.heuristic, cancel, deadline);
+        for i in 0..=self.max_depth {
+            let res = solve(s, &heuristic, self.moves, Metric::FaceTurn, i);
+            if let Some(solution) = res {
+                return Some(vec![solution]);
+            } else if heuristic.is_cancelled() {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// A step that falls back on a sequence of other steps when its main step
+/// fails.
+///
+/// This is useful when a step is usually cheap to solve directly, but can be
+/// very deep for some states.
+pub struct FallbackStep {
+    primary: Box<Step>,
+    fallback: Vec<Box<Step>>
+}
+
+impl FallbackStep {
+    pub fn new(primary: Box<Step>, fallback: Vec<Box<Step>>) -> FallbackStep {
+        FallbackStep{
+            primary: primary,
+            fallback: fallback
+        }
+    }
+
+    fn solve_fallback(
+        &self,
+        s: &State,
+        cancel: &AtomicBool,
+        deadline: Option<Instant>
+    ) -> Option<Vec<Algo>> {
+        let mut state = s.clone();
+        let mut parts = Vec::new();
+        for step in &self.fallback {
+            for part in step.solve(&state, cancel, deadline)? {
+                part.apply(&mut state);
+                parts.push(part);
+            }
+        }
+        Some(parts)
+    }
+}
+
+impl Step for FallbackStep {
+    fn name(&self) -> String {
+        let names: Vec<String> = self.fallback.iter().map(|s| s.name()).collect();
+        format!("{} (or {})", self.primary.name(), names.join(", "))
+    }
+
+    fn max_depth(&self) -> u8 {
+        self.fallback.iter().map(|s| s.max_depth()).fold(self.primary.max_depth(), u8::max)
+    }
+
+    fn solve(
+        &self,
+        s: &State,
+        cancel: &AtomicBool,
+        deadline: Option<Instant>
+    ) -> Option<Vec<Algo>> {
+        if let Some(parts) = self.primary.solve(s, cancel, deadline) {
+            return Some(parts);
+        }
+        self.solve_fallback(s, cancel, deadline)
+    }
+
+    fn candidates(
+        &self,
+        s: &State,
+        k: usize,
+        cancel: &AtomicBool,
+        deadline: Option<Instant>
+    ) -> Vec<Vec<Algo>> {
+        let res = self.primary.candidates(s, k, cancel, deadline);
+        if res.len() > 0 {
+            return res;
+        }
+        self.solve_fallback(s, cancel, deadline).into_iter().take(k).collect()
+    }
+}
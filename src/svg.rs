@@ -0,0 +1,306 @@
+//! Drawing states and algorithms as SVG images.
+
+use std::f64::consts::PI;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use super::moves::Algo;
+use super::state::{Direction, Face, State};
+
+/// The side length of a sticker.
+const STICKER_SIZE: f64 = 20.0;
+
+/// The space around an image, and between the frames of an algorithm.
+const MARGIN: f64 = 10.0;
+
+/// The height of a label, including space below it.
+const LABEL_HEIGHT: f64 = 16.0;
+
+/// A way of drawing the puzzle.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SvgView {
+    /// All six faces, unfolded into a cross.
+    Net,
+
+    /// The U, F and R faces, seen from a corner.
+    Isometric
+}
+
+impl FromStr for SvgView {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SvgView, String> {
+        match s {
+            "net" => Ok(SvgView::Net),
+            "isometric" => Ok(SvgView::Isometric),
+            _ => Err(format!("unknown view: {}", s))
+        }
+    }
+}
+
+impl Display for SvgView {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &SvgView::Net => write!(f, "net"),
+            &SvgView::Isometric => write!(f, "isometric")
+        }
+    }
+}
+
+/// Draw a state as an SVG image.
+///
+/// Arrows are drawn as arcs that point in their rotational direction, and
+/// locked faces are outlined in red.
+pub fn render_svg(state: &State, view: SvgView) -> String {
+    let (width, height) = frame_size(view);
+    let mut body = String::new();
+    draw_frame(state, view, &mut body);
+    svg_document(width + 2.0 * MARGIN, height + 2.0 * MARGIN, &body)
+}
+
+/// Draw an algorithm as a strip of frames, starting with the given state
+/// and followed by the state after every move.
+///
+/// Each frame is captioned with the move that led to it.
+pub fn render_algo_svg(state: &State, algo: &Algo, view: SvgView) -> String {
+    let (width, height) = frame_size(view);
+    let mut body = String::new();
+    let mut state = state.clone();
+    for i in 0..(algo.0.len() + 1) {
+        let caption = if i == 0 {
+            "start".to_owned()
+        } else {
+            algo.0[i - 1].apply(&mut state);
+            format!("{}", algo.0[i - 1])
+        };
+        let x = MARGIN + (i as f64) * (width + MARGIN);
+        body.push_str(&format!("<g transform=\"translate({:.1},{:.1})\">\n", x, MARGIN));
+        body.push_str(&text(width / 2.0, LABEL_HEIGHT - 4.0, "middle", &caption));
+        body.push_str(&format!("<g transform=\"translate(0,{:.1})\">\n", LABEL_HEIGHT));
+        draw_frame(&state, view, &mut body);
+        body.push_str("</g>\n</g>\n");
+    }
+    let frames = (algo.0.len() + 1) as f64;
+    svg_document(frames * (width + MARGIN) + MARGIN, height + LABEL_HEIGHT + 2.0 * MARGIN,
+        &body)
+}
+
+/// A map from the coordinates of a face (in stickers, read from the top
+/// left) to image coordinates.
+struct FaceMap {
+    origin: (f64, f64),
+    u: (f64, f64),
+    v: (f64, f64)
+}
+
+impl FaceMap {
+    fn map(&self, u: f64, v: f64) -> (f64, f64) {
+        (self.origin.0 + u * self.u.0 + v * self.v.0, self.origin.1 + u * self.u.1 + v * self.v.1)
+    }
+}
+
+/// Get the size of a single drawing of the puzzle, excluding the margin.
+fn frame_size(view: SvgView) -> (f64, f64) {
+    match view {
+        SvgView::Net => (4.0 * net_face_size(), 3.0 * net_face_size()),
+        SvgView::Isometric => {
+            let cube = 3.0 * STICKER_SIZE;
+            (2.0 * cube * (PI / 6.0).cos(), 2.0 * cube)
+        }
+    }
+}
+
+/// Get the space taken up by a face in the net, including its label.
+fn net_face_size() -> f64 {
+    3.0 * STICKER_SIZE + LABEL_HEIGHT
+}
+
+/// Get the faces to draw, along with their positions in the image.
+fn face_maps(view: SvgView) -> Vec<(Face, FaceMap)> {
+    use Face::*;
+    match view {
+        SvgView::Net => {
+            let size = net_face_size();
+            [(U, 1.0, 0.0), (L, 0.0, 1.0), (F, 1.0, 1.0), (R, 2.0, 1.0), (B, 3.0, 1.0),
+             (D, 1.0, 2.0)].iter().map(|&(face, col, row)| {
+                (face, FaceMap{
+                    origin: (col * size, row * size + LABEL_HEIGHT),
+                    u: (STICKER_SIZE, 0.0),
+                    v: (0.0, STICKER_SIZE)
+                })
+            }).collect()
+        },
+        SvgView::Isometric => {
+            // Project a point (x, y, z) on a cube with side 3, where x points
+            // to R, y points to U, and z points to F.
+            let (width, height) = frame_size(view);
+            let cos = (PI / 6.0).cos() * STICKER_SIZE;
+            let sin = (PI / 6.0).sin() * STICKER_SIZE;
+            let project = |x: f64, y: f64, z: f64| {
+                (width / 2.0 + (x - z) * cos, height / 2.0 - y * STICKER_SIZE + (x + z) * sin)
+            };
+            let face_map = |origin: (f64, f64, f64), u: (f64, f64, f64), v: (f64, f64, f64)| {
+                let o = project(origin.0, origin.1, origin.2);
+                let pu = project(origin.0 + u.0, origin.1 + u.1, origin.2 + u.2);
+                let pv = project(origin.0 + v.0, origin.1 + v.1, origin.2 + v.2);
+                FaceMap{origin: o, u: (pu.0 - o.0, pu.1 - o.1), v: (pv.0 - o.0, pv.1 - o.1)}
+            };
+            vec![
+                (U, face_map((0.0, 3.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, 1.0))),
+                (F, face_map((0.0, 3.0, 3.0), (1.0, 0.0, 0.0), (0.0, -1.0, 0.0))),
+                (R, face_map((3.0, 3.0, 3.0), (0.0, 0.0, -1.0), (0.0, -1.0, 0.0)))
+            ]
+        }
+    }
+}
+
+/// Draw the puzzle with its top left corner at the origin.
+fn draw_frame(state: &State, view: SvgView, out: &mut String) {
+    for (face, face_map) in face_maps(view) {
+        if view == SvgView::Net {
+            let (x, y) = face_map.map(0.0, 0.0);
+            let label = if state.is_locked(face) {
+                format!("{} (locked)", face)
+            } else {
+                format!("{}", face)
+            };
+            out.push_str(&text(x, y - 4.0, "start", &label));
+        }
+        for idx in 0..9 {
+            let (col, row) = ((idx % 3) as f64, (idx / 3) as f64);
+            let (color, direction) = match idx {
+                4 => (face, Direction::Neutral),
+                _ => {
+                    let sticker = state.face(face)[if idx < 4 { idx } else { idx - 1 }];
+                    (sticker.face, sticker.direction)
+                }
+            };
+            let corners = [(col, row), (col + 1.0, row), (col + 1.0, row + 1.0),
+                (col, row + 1.0)];
+            out.push_str(&format!(
+                "<polygon class=\"sticker\" points=\"{}\" fill=\"{}\" stroke=\"#333\"/>\n",
+                points(&face_map, &corners),
+                fill_color(color)
+            ));
+            if direction != Direction::Neutral {
+                draw_arrow(&face_map, col + 0.5, row + 0.5, direction, arrow_color(color), out);
+            }
+        }
+        if state.is_locked(face) {
+            let corners = [(0.0, 0.0), (3.0, 0.0), (3.0, 3.0), (0.0, 3.0)];
+            out.push_str(&format!(
+                "<polygon class=\"locked\" points=\"{}\" fill=\"none\" stroke=\"#e00\" \
+                 stroke-width=\"3\"/>\n",
+                points(&face_map, &corners)
+            ));
+        }
+    }
+}
+
+/// Draw a circular arrow around a point on a face.
+///
+/// Since face coordinates are read from the top left, increasing angles go
+/// clockwise when the face is viewed from the outside.
+fn draw_arrow(
+    face_map: &FaceMap,
+    u: f64,
+    v: f64,
+    direction: Direction,
+    color: &str,
+    out: &mut String
+) {
+    let radius = 0.25;
+    let mut arc: Vec<(f64, f64)> = (0..13).map(|i| {
+        let angle = -PI / 2.0 + (i as f64) * (1.5 * PI / 12.0);
+        (u + radius * angle.cos(), v + radius * angle.sin())
+    }).collect();
+    if direction == Direction::Counter {
+        arc.reverse();
+    }
+
+    // The arrowhead points along the last segment of the arc.
+    let (end, before) = (arc[arc.len() - 1], arc[arc.len() - 2]);
+    let length = ((end.0 - before.0).powi(2) + (end.1 - before.1).powi(2)).sqrt();
+    let (du, dv) = ((end.0 - before.0) / length * 0.15, (end.1 - before.1) / length * 0.15);
+    let head = [(end.0 + du, end.1 + dv), (end.0 - dv * 0.7, end.1 + du * 0.7),
+        (end.0 + dv * 0.7, end.1 - du * 0.7)];
+
+    out.push_str(&format!(
+        "<polyline class=\"arrow\" points=\"{}\" fill=\"none\" stroke=\"{}\" \
+         stroke-width=\"2\"/>\n",
+        points(face_map, &arc),
+        color
+    ));
+    out.push_str(&format!("<polygon points=\"{}\" fill=\"{}\"/>\n", points(face_map, &head),
+        color));
+}
+
+/// Format face coordinates as an SVG list of image points.
+fn points(face_map: &FaceMap, coords: &[(f64, f64)]) -> String {
+    let points: Vec<String> = coords.iter().map(|&(u, v)| {
+        let (x, y) = face_map.map(u, v);
+        format!("{:.1},{:.1}", x, y)
+    }).collect();
+    points.join(" ")
+}
+
+fn text(x: f64, y: f64, anchor: &str, content: &str) -> String {
+    format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"{}\" font-family=\"sans-serif\" \
+             font-size=\"12\">{}</text>\n", x, y, anchor, content)
+}
+
+fn svg_document(width: f64, height: f64, body: &str) -> String {
+    format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" \
+             viewBox=\"0 0 {:.0} {:.0}\">\n<g transform=\"translate({:.1},{:.1})\">\n{}</g>\n\
+             </svg>\n", width, height, width, height, MARGIN, MARGIN, body)
+}
+
+/// Get the color of a face, following the scheme documented on Face.
+fn fill_color(face: Face) -> &'static str {
+    use Face::*;
+    match face {
+        U => "#ffd500",
+        D => "#c41e3a",
+        F => "#0051ba",
+        B => "#009e60",
+        R => "#222222",
+        L => "#ffffff"
+    }
+}
+
+/// Get a color for arrows that stands out on a face's color.
+fn arrow_color(face: Face) -> &'static str {
+    use Face::*;
+    match face {
+        U | L => "#000000",
+        _ => "#ffffff"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test the elements drawn for each view.
+    #[test]
+    fn svg_elements() {
+        let solved = render_svg(&State::default(), SvgView::Net);
+        assert!(solved.starts_with("<svg "));
+        assert_eq!(solved.matches("class=\"sticker\"").count(), 54);
+        assert_eq!(solved.matches("class=\"arrow\"").count(), 12);
+        assert_eq!(solved.matches("class=\"locked\"").count(), 0);
+
+        let state = "F".parse::<Algo>().unwrap().state();
+        let net = render_svg(&state, SvgView::Net);
+        assert_eq!(net.matches("class=\"locked\"").count(), 2);
+        let isometric = render_svg(&state, SvgView::Isometric);
+        assert_eq!(isometric.matches("class=\"sticker\"").count(), 27);
+        assert_eq!(isometric.matches("class=\"locked\"").count(), 1);
+
+        let algo: Algo = "R' U".parse().unwrap();
+        let strip = render_algo_svg(&algo.inverse().state(), &algo, SvgView::Isometric);
+        assert_eq!(strip.matches("class=\"sticker\"").count(), 27 * 3);
+        assert!(strip.contains(">R'</text>"));
+    }
+}
@@ -0,0 +1,76 @@
+//! A tool for drawing the locky puzzle as SVG images.
+
+extern crate clap;
+extern crate locky_puzzle;
+
+use std::fs::File;
+use std::io::Write;
+use std::process::exit;
+
+use clap::{App, Arg};
+use locky_puzzle::{Algo, State, SvgView, render_algo_svg, render_svg};
+
+fn main() {
+    let matches = App::new("locky-render")
+        .arg(Arg::with_name("scramble")
+            .long("scramble")
+            .value_name("ALGO")
+            .takes_value(true)
+            .conflicts_with("state")
+            .help("Draw the state after a scramble"))
+        .arg(Arg::with_name("state")
+            .long("state")
+            .value_name("STATE")
+            .takes_value(true)
+            .help("Draw a state, in facelet or display format"))
+        .arg(Arg::with_name("algo")
+            .long("algo")
+            .value_name("ALGO")
+            .takes_value(true)
+            .help("Draw a strip of frames for each move of an algorithm"))
+        .arg(Arg::with_name("view")
+            .long("view")
+            .value_name("VIEW")
+            .takes_value(true)
+            .help("Draw a net or an isometric view (default: net)"))
+        .arg(Arg::with_name("output")
+            .long("output")
+            .short("o")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("Write the image to FILE instead of standard output"))
+        .get_matches();
+    if let Err(e) = run(&matches) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+fn run(matches: &clap::ArgMatches) -> Result<(), String> {
+    let state: State = if let Some(scramble) = matches.value_of("scramble") {
+        let algo: Algo = scramble.parse().map_err(|e| format!("parse scramble: {}", e))?;
+        algo.legal_state().map_err(|e| format!("illegal scramble: {}", e))?
+    } else if let Some(state) = matches.value_of("state") {
+        state.parse().map_err(|e| format!("parse state: {}", e))?
+    } else {
+        State::default()
+    };
+    let view: SvgView = matches.value_of("view").unwrap_or("net").parse()
+        .map_err(|e| format!("bad view argument: {}", e))?;
+    let svg = if let Some(algo) = matches.value_of("algo") {
+        let algo: Algo = algo.parse().map_err(|e| format!("parse algo: {}", e))?;
+        render_algo_svg(&state, &algo, view)
+    } else {
+        render_svg(&state, view)
+    };
+    match matches.value_of("output") {
+        Some(path) => {
+            let mut file = File::create(path).map_err(|e| format!("create {}: {}", path, e))?;
+            file.write_all(svg.as_bytes()).map_err(|e| format!("write {}: {}", path, e))
+        },
+        None => {
+            print!("{}", svg);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Finding solutions to suggest the next move.
+
+use locky_puzzle::{Algo, ArrowAxisProj, CornerProj, Heuristic, MaxHeuristic, Metric, MoveSet,
+    MultiStep, ProjHeuristic, State, solve};
+
+/// A solver which only generates its tables once they are needed.
+pub struct Hints {
+    depth: u8,
+    heuristic: Option<MaxHeuristic<Box<Heuristic>>>,
+    multi: Option<MultiStep>
+}
+
+impl Hints {
+    /// Create a solver which searches for optimal solutions up to the given
+    /// depth before falling back to the multi-step solver.
+    pub fn new(depth: u8) -> Hints {
+        Hints{depth: depth, heuristic: None, multi: None}
+    }
+
+    /// Find a solution for a state.
+    pub fn solve(&mut self, state: &State) -> Result<Algo, String> {
+        if self.heuristic.is_none() {
+            println!("Generating heuristic...");
+            let heuristics: Vec<Box<Heuristic>> = vec![
+                Box::new(ProjHeuristic::<CornerProj>::generate(7, MoveSet::all(),
+                    Metric::FaceTurn)),
+                Box::new(ProjHeuristic::<ArrowAxisProj>::generate(7, MoveSet::all(),
+                    Metric::FaceTurn))
+            ];
+            self.heuristic = Some(MaxHeuristic(heuristics));
+        }
+        let heuristic = self.heuristic.as_ref().unwrap();
+        for depth in 0..=self.depth {
+            if let Some(solution) = solve(state, heuristic, MoveSet::all(), Metric::FaceTurn,
+                                          depth) {
+                return Ok(solution);
+            }
+        }
+
+        println!("No solution within {} moves, using the multi-step solver...", self.depth);
+        if self.multi.is_none() {
+            println!("Generating solver...");
+            self.multi = Some(MultiStep::generate_default());
+        }
+        let (solution, _) = self.multi.as_ref().unwrap().solve(state).map_err(|e| e.to_string())?;
+        Ok(solution)
+    }
+}
@@ -0,0 +1,230 @@
+//! An interactive simulator for the locky puzzle.
+
+extern crate clap;
+extern crate locky_puzzle;
+
+mod hints;
+mod session;
+
+use std::fs::File;
+use std::io::{BufRead, Read, Write, stdin, stdout};
+use std::process::exit;
+
+use clap::{App, Arg, ArgMatches};
+use locky_puzzle::{Algo, Move, State, TextStyle, render_net};
+
+use hints::Hints;
+use session::Session;
+
+const HELP: &str = "Commands:
+  R U2 F'       make moves, stopping at the first locked face
+  undo [NUM]    undo the last move (or NUM moves)
+  redo [NUM]    redo the last undone move (or NUM moves)
+  hint          suggest the next move of a solution
+  reset         go back to the starting state
+  history       print the starting state and the moves made so far
+  save FILE     save the session to FILE
+  load FILE     load a session from FILE
+  show          draw the current state
+  help          print this message
+  quit          exit";
+
+fn main() {
+    let matches = App::new("locky-repl")
+        .arg(Arg::with_name("scramble")
+            .long("scramble")
+            .value_name("ALGO")
+            .takes_value(true)
+            .conflicts_with("state")
+            .help("Start from the state after a scramble"))
+        .arg(Arg::with_name("state")
+            .long("state")
+            .value_name("STATE")
+            .takes_value(true)
+            .help("Start from a state, in facelet or display format"))
+        .arg(Arg::with_name("net")
+            .long("net")
+            .value_name("STYLE")
+            .takes_value(true)
+            .help("Draw the puzzle with 'ascii' or 'ansi' stickers (default: ascii)"))
+        .arg(Arg::with_name("hint-depth")
+            .long("hint-depth")
+            .value_name("NUM")
+            .takes_value(true)
+            .help("Search for optimal hints up to NUM moves (default: 14)"))
+        .get_matches();
+    if let Err(e) = run(&matches) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let start: State = if let Some(scramble) = matches.value_of("scramble") {
+        let algo: Algo = scramble.parse().map_err(|e| format!("parse scramble: {}", e))?;
+        algo.legal_state().map_err(|e| format!("illegal scramble: {}", e))?
+    } else if let Some(state) = matches.value_of("state") {
+        state.parse().map_err(|e| format!("parse state: {}", e))?
+    } else {
+        State::default()
+    };
+    let style = matches.value_of("net").unwrap_or("ascii").parse()
+        .map_err(|e| format!("bad net argument: {}", e))?;
+    let hint_depth = matches.value_of("hint-depth").unwrap_or("14").parse()
+        .map_err(|e| format!("bad hint-depth argument: {}", e))?;
+    let mut repl = Repl{
+        session: Session::new(start),
+        style: style,
+        hints: Hints::new(hint_depth),
+        plan: Vec::new()
+    };
+
+    println!("Enter moves (e.g. R U2 F') or \"help\" for a list of commands.");
+    repl.show();
+    let stdin = stdin();
+    loop {
+        print!("> ");
+        stdout().flush().map_err(|e| format!("write prompt: {}", e))?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).map_err(|e| format!("read command: {}", e))? == 0 {
+            println!("");
+            return Ok(());
+        }
+        match repl.run_command(line.trim()) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(e) => println!("{}", e)
+        }
+    }
+}
+
+/// The state of the simulator.
+struct Repl {
+    session: Session,
+    style: TextStyle,
+    hints: Hints,
+
+    /// The remaining moves of the last hinted solution, as long as the user
+    /// has been following it.
+    plan: Vec<Move>
+}
+
+impl Repl {
+    /// Run a command, returning false if the simulator should exit.
+    fn run_command(&mut self, line: &str) -> Result<bool, String> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return Ok(true)
+        };
+        let arg = words.next();
+        match command {
+            "quit" | "exit" => return Ok(false),
+            "help" => println!("{}", HELP),
+            "show" => self.show(),
+            "history" => {
+                println!("Start: {}", self.session.start.to_facelet_string());
+                println!("Moves: {}", self.session.moves());
+            },
+            "undo" => {
+                for _ in 0..parse_count(arg)? {
+                    match self.session.undo() {
+                        Some(m) => println!("Undid {}", m),
+                        None => {
+                            println!("Nothing to undo");
+                            break;
+                        }
+                    }
+                }
+                self.plan.clear();
+                self.show();
+            },
+            "redo" => {
+                for _ in 0..parse_count(arg)? {
+                    match self.session.redo() {
+                        Some(m) => println!("Redid {}", m),
+                        None => {
+                            println!("Nothing to redo");
+                            break;
+                        }
+                    }
+                }
+                self.plan.clear();
+                self.show();
+            },
+            "hint" => self.hint()?,
+            "reset" => {
+                self.session = Session::new(self.session.start.clone());
+                self.plan.clear();
+                self.show();
+            },
+            "save" => {
+                let path = arg.ok_or("usage: save FILE")?;
+                let mut file = File::create(path).map_err(|e| format!("create {}: {}", path, e))?;
+                file.write_all(self.session.to_string().as_bytes())
+                    .map_err(|e| format!("write {}: {}", path, e))?;
+                println!("Saved session to {}", path);
+            },
+            "load" => {
+                let path = arg.ok_or("usage: load FILE")?;
+                let mut contents = String::new();
+                File::open(path).and_then(|mut f| f.read_to_string(&mut contents))
+                    .map_err(|e| format!("read {}: {}", path, e))?;
+                self.session = contents.parse().map_err(|e| format!("load {}: {}", path, e))?;
+                self.plan.clear();
+                println!("Loaded session from {}", path);
+                self.show();
+            },
+            _ => self.make_moves(line)?
+        }
+        Ok(true)
+    }
+
+    /// Make a sequence of moves, stopping at the first locked face.
+    fn make_moves(&mut self, line: &str) -> Result<(), String> {
+        let algo = line.parse::<Algo>()
+            .map_err(|e| format!("unknown command or move: {}", e.move_str()))?;
+        for m in algo.0 {
+            if let Err(e) = self.session.apply(m) {
+                self.show();
+                return Err(e);
+            }
+            if self.plan.first() == Some(&m) {
+                self.plan.remove(0);
+            } else {
+                self.plan.clear();
+            }
+        }
+        self.show();
+        Ok(())
+    }
+
+    /// Suggest the next move, reusing the last solution if the user has
+    /// followed it so far.
+    fn hint(&mut self) -> Result<(), String> {
+        if self.session.state().is_solved() {
+            println!("The puzzle is already solved");
+            return Ok(());
+        }
+        if self.plan.is_empty() {
+            self.plan = self.hints.solve(self.session.state())?.0;
+        }
+        println!("Hint: {} (solution: {})", self.plan[0], Algo(self.plan.clone()));
+        Ok(())
+    }
+
+    fn show(&self) {
+        print!("{}", render_net(self.session.state(), self.style));
+        if self.session.state().is_solved() {
+            println!("Solved!");
+        }
+    }
+}
+
+/// Parse the optional count of an undo or redo command.
+fn parse_count(arg: Option<&str>) -> Result<usize, String> {
+    match arg {
+        Some(s) => s.parse().map_err(|_| format!("bad count: {}", s)),
+        None => Ok(1)
+    }
+}
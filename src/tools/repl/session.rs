@@ -0,0 +1,156 @@
+//! The moves made by the user, along with undo and redo history.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use locky_puzzle::{Algo, Move, State};
+
+/// A sequence of legal moves from a starting state.
+pub struct Session {
+    pub start: State,
+    moves: Vec<Move>,
+    undone: Vec<Move>,
+    state: State
+}
+
+impl Session {
+    pub fn new(start: State) -> Session {
+        Session{start: start.clone(), moves: Vec::new(), undone: Vec::new(), state: start}
+    }
+
+    /// Get the state after every move.
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Get the moves made so far.
+    pub fn moves(&self) -> Algo {
+        Algo(self.moves.clone())
+    }
+
+    /// Make a move, unless its face is locked.
+    ///
+    /// Making a move clears the moves that can be redone.
+    pub fn apply(&mut self, m: Move) -> Result<(), String> {
+        if self.state.is_locked(m.face) {
+            return Err(format!("cannot turn {}: the {} face is locked", m, m.face));
+        }
+        m.apply(&mut self.state);
+        self.moves.push(m);
+        self.undone.clear();
+        Ok(())
+    }
+
+    /// Undo the last move, returning it.
+    ///
+    /// The inverse move is always legal, see Algo::inverse().
+    pub fn undo(&mut self) -> Option<Move> {
+        let m = self.moves.pop()?;
+        m.inverse().apply(&mut self.state);
+        self.undone.push(m);
+        Some(m)
+    }
+
+    /// Redo the last undone move, returning it.
+    ///
+    /// An undone move can always be made again, since the state is the same
+    /// as when it was first made.
+    pub fn redo(&mut self) -> Option<Move> {
+        let m = self.undone.pop()?;
+        m.apply(&mut self.state);
+        self.moves.push(m);
+        Some(m)
+    }
+}
+
+/// Sessions are saved as a starting state (in facelet format) and a list of
+/// moves, e.g.:
+///
+/// ```text
+/// start: UU^UUUUUU^UDD^DDDDDD^D...
+/// moves: R U2 F'
+/// ```
+impl Display for Session {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        writeln!(f, "start: {}", self.start.to_facelet_string())?;
+        writeln!(f, "moves: {}", self.moves())
+    }
+}
+
+impl FromStr for Session {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Session, String> {
+        let mut start = State::default();
+        let mut moves = Algo(Vec::new());
+        for line in s.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            let mut parts = line.splitn(2, ':');
+            let (key, value) = (parts.next().unwrap(), parts.next().unwrap_or("").trim());
+            match key {
+                "start" => start = value.parse().map_err(|e| format!("parse start: {}", e))?,
+                "moves" => moves = value.parse().map_err(|e| format!("parse moves: {}", e))?,
+                _ => return Err(format!("unknown session field: {}", key))
+            }
+        }
+        let mut res = Session::new(start);
+        for m in moves.0 {
+            res.apply(m)?;
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that undo and redo walk back and forth through the moves.
+    #[test]
+    fn undo_redo() {
+        let mut session = Session::new(State::default());
+        let algo: Algo = "B D2 B' U2 L2".parse().unwrap();
+        for &m in &algo.0 {
+            session.apply(m).unwrap();
+        }
+        assert_eq!(session.state(), &algo.state());
+
+        assert_eq!(session.undo(), Some(algo.0[4]));
+        assert_eq!(session.undo(), Some(algo.0[3]));
+        let prefix = Algo(algo.0[..3].to_vec());
+        assert_eq!(session.moves(), prefix);
+        assert_eq!(session.state(), &prefix.state());
+
+        assert_eq!(session.redo(), Some(algo.0[3]));
+        assert_eq!(session.redo(), Some(algo.0[4]));
+        assert_eq!(session.redo(), None);
+        assert_eq!(session.state(), &algo.state());
+
+        session.undo();
+        session.apply(algo.0[4]).unwrap();
+        assert_eq!(session.redo(), None);
+        assert_eq!(session.moves(), algo);
+
+        while session.undo().is_some() {}
+        assert_eq!(session.state(), &State::default());
+    }
+
+    /// Test that a saved session loads with the same start and moves.
+    #[test]
+    fn save_load() {
+        let start = "B D2".parse::<Algo>().unwrap().state();
+        let mut session = Session::new(start.clone());
+        session.apply("B'".parse().unwrap()).unwrap();
+        session.apply("U2".parse().unwrap()).unwrap();
+
+        let loaded: Session = session.to_string().parse().unwrap();
+        assert_eq!(loaded.start, start);
+        assert_eq!(loaded.moves(), session.moves());
+        assert_eq!(loaded.state(), session.state());
+
+        let face = start.locked_faces()[0];
+        let illegal = format!("start: {}\nmoves: {}", start.to_facelet_string(), face);
+        assert!(illegal.parse::<Session>().is_err());
+        assert!("moves: R\nfoo: bar".parse::<Session>().is_err());
+    }
+}
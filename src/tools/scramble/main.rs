@@ -2,9 +2,21 @@
 
 extern crate clap;
 extern crate locky_puzzle;
+extern crate rand;
+#[macro_use]
+extern crate serde_json;
 
-use clap::{App, Arg};
-use locky_puzzle::scramble;
+use std::process::exit;
+use std::str::FromStr;
+
+use clap::{App, Arg, ArgMatches};
+use locky_puzzle::{Algo, ArrowAxisProj, CornerProj, Heuristic, MaxHeuristic, Metric, MoveSet,
+    MultiStep, ProjHeuristic, State, algo_to_json, filtered_scramble, has_min_distance,
+    random_state_scramble, seeded_rng, state_to_json};
+use rand::random;
+
+/// The number of scrambles to try before giving up on the filters.
+const MAX_ATTEMPTS: usize = 10000;
 
 fn main() {
     let matches = App::new("locky-scramble")
@@ -12,7 +24,128 @@ fn main() {
             .long("moves")
             .takes_value(true)
             .help("Solve a specific a sequence of moves"))
+        .arg(Arg::with_name("random-state")
+            .long("random-state")
+            .help("Scramble to a uniformly random state (slow, gives long scrambles)"))
+        .arg(Arg::with_name("seed")
+            .long("seed")
+            .value_name("NUM")
+            .takes_value(true)
+            .help("Seed the random number generator to reproduce scrambles"))
+        .arg(Arg::with_name("count")
+            .long("count")
+            .value_name("NUM")
+            .takes_value(true)
+            .help("Set the number of scrambles to print (default: 1)"))
+        .arg(Arg::with_name("min-distance")
+            .long("min-distance")
+            .value_name("NUM")
+            .takes_value(true)
+            .help("Only print scrambles that are at least NUM moves from solved (slow)"))
+        .arg(Arg::with_name("min-locked")
+            .long("min-locked")
+            .value_name("NUM")
+            .takes_value(true)
+            .help("Only print scrambles that leave at least NUM faces locked"))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .value_name("FORMAT")
+            .takes_value(true)
+            .help("Print scrambles as text or as a json object (default: text)"))
         .get_matches();
-    let moves = matches.value_of("moves").unwrap_or("25").parse().unwrap();
-    println!("{}", scramble(moves))
+    let json = match matches.value_of("format").unwrap_or("text") {
+        "text" => false,
+        "json" => true,
+        f => {
+            eprintln!("bad format argument: {}", f);
+            exit(1);
+        }
+    };
+    if let Err(e) = run(&matches, json) {
+        if json {
+            println!("{}", json!({"error": {"kind": "usage", "message": e}}));
+        } else {
+            eprintln!("{}", e);
+        }
+        exit(1);
+    }
+}
+
+fn run(matches: &ArgMatches, json: bool) -> Result<(), String> {
+    let seed = match matches.value_of("seed") {
+        Some(s) => parse_arg("seed", s)?,
+        None => {
+            // Report the seed so that the scrambles can be reproduced. JSON
+            // output already includes it.
+            let seed = random();
+            if !json {
+                eprintln!("Seed: {}", seed);
+            }
+            seed
+        }
+    };
+    let count: usize = parse_arg("count", matches.value_of("count").unwrap_or("1"))?;
+    let min_distance: u8 = parse_arg("min-distance",
+        matches.value_of("min-distance").unwrap_or("0"))?;
+    let min_locked: usize = parse_arg("min-locked",
+        matches.value_of("min-locked").unwrap_or("0"))?;
+    let moves: usize = parse_arg("moves", matches.value_of("moves").unwrap_or("25"))?;
+    let random_state = matches.is_present("random-state");
+    if min_locked > 6 {
+        return Err("min-locked must be at most 6".to_owned());
+    } else if !random_state && min_distance as usize > moves {
+        // Undoing the scramble solves the puzzle in the same number of moves.
+        return Err("min-distance must be at most the number of moves".to_owned());
+    }
+    let mut rng = seeded_rng(seed);
+
+    let mut heuristics: Vec<Box<Heuristic>> = Vec::new();
+    if min_distance > 0 {
+        heuristics.push(Box::new(ProjHeuristic::<CornerProj>::generate(7, MoveSet::all(),
+            Metric::FaceTurn)));
+        heuristics.push(Box::new(ProjHeuristic::<ArrowAxisProj>::generate(7, MoveSet::all(),
+            Metric::FaceTurn)));
+    }
+    let heuristic = MaxHeuristic(heuristics);
+    let accept = |s: &State| {
+        s.num_locked() >= min_locked && has_min_distance(s, &heuristic, min_distance)
+    };
+
+    let solver = if random_state {
+        Some(MultiStep::generate_default())
+    } else {
+        None
+    };
+    let mut scrambles = Vec::new();
+    for _ in 0..count {
+        let scramble = match solver {
+            // Each attempt samples a single position, which may be rejected.
+            Some(ref solver) => (0..MAX_ATTEMPTS)
+                .filter_map(|_| random_state_scramble(solver, &mut rng, 1))
+                .find(|scramble| accept(&scramble.state())),
+            None => filtered_scramble(moves, &mut rng, MAX_ATTEMPTS, &accept)
+        };
+        let scramble = scramble.ok_or_else(|| {
+            format!("no scramble satisfied the filters after {} attempts", MAX_ATTEMPTS)
+        })?;
+        if json {
+            scrambles.push(scramble_to_json(&scramble));
+        } else {
+            println!("{}", scramble);
+        }
+    }
+    if json {
+        println!("{}", json!({"seed": seed, "scrambles": scrambles}));
+    }
+    Ok(())
+}
+
+fn scramble_to_json(scramble: &Algo) -> serde_json::Value {
+    let mut res = algo_to_json(scramble);
+    res["state"] = state_to_json(&scramble.state());
+    res
+}
+
+fn parse_arg<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("bad {} argument: {}", name, value))
 }
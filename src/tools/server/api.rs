@@ -0,0 +1,365 @@
+//! The JSON endpoints of the server.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, Sender, channel};
+use std::thread::spawn;
+use std::time::{Duration, Instant};
+
+use locky_puzzle::{Algo, CancelHeuristic, Goal, Heuristic, LockProj, MaxHeuristic, Metric,
+    MoveSet, MultiStep, MultiStepError, Proj, State, SvgView, algo_to_json, error_to_json,
+    render_algo_svg, render_svg, scramble_with_rng, seeded_rng, solve, state_to_json, verify,
+    verify_error_to_json};
+use rand::random;
+use serde_json::{Value, from_slice};
+
+use http::{Request, Response};
+
+/// The most scrambles that one request can ask for.
+const MAX_COUNT: u64 = 1000;
+
+/// The longest scrambles that a request can ask for.
+const MAX_MOVES: u64 = 1000;
+
+/// The solvers, which are generated once and shared by every request.
+pub struct Api {
+    pub heuristic: MaxHeuristic<Box<Heuristic>>,
+    pub multi: Arc<MultiStep>,
+    pub time_limit: Duration
+}
+
+/// A failed request, which is reported as {"error": ...}.
+pub struct ApiError {
+    status: u16,
+    error: Value
+}
+
+impl ApiError {
+    fn new(status: u16, kind: &str, message: &str) -> ApiError {
+        ApiError{status: status, error: json!({"kind": kind, "message": message})}
+    }
+
+    fn bad_request(message: &str) -> ApiError {
+        ApiError::new(400, "bad_request", message)
+    }
+
+    fn timeout(limit: Duration) -> ApiError {
+        ApiError::new(503, "timeout", &format!("no solution within {} ms", millis(limit)))
+    }
+}
+
+impl Api {
+    /// Route a request to its endpoint.
+    pub fn handle(&self, request: &Request) -> Response {
+        let result = match (request.method.as_str(), request.path.as_str()) {
+            ("OPTIONS", _) => return Response{status: 204, content_type: "text/plain",
+                body: String::new()},
+            ("GET", "/health") => Ok(json!({"status": "ok"})),
+            ("POST", "/scramble") => parse_body(request).and_then(|b| self.scramble(&b)),
+            ("POST", "/solve/optimal") => parse_body(request).and_then(|b| self.optimal(&b)),
+            ("POST", "/solve/multi-step") => {
+                parse_body(request).and_then(|b| self.multi_step(&b))
+            },
+            ("POST", "/validate") => parse_body(request).and_then(|b| self.validate(&b)),
+            ("POST", "/render") => match parse_body(request).and_then(|b| self.render(&b)) {
+                Ok(svg) => return Response::svg(svg),
+                Err(e) => Err(e)
+            },
+            (_, "/health") | (_, "/scramble") | (_, "/solve/optimal") |
+            (_, "/solve/multi-step") | (_, "/validate") | (_, "/render") => {
+                Err(ApiError::new(405, "method_not_allowed",
+                    &format!("{} is not allowed for {}", request.method, request.path)))
+            },
+            _ => Err(ApiError::new(404, "not_found",
+                &format!("unknown endpoint: {}", request.path)))
+        };
+        match result {
+            Ok(body) => Response::json(200, &body),
+            Err(e) => Response::json(e.status, &json!({"error": e.error}))
+        }
+    }
+
+    /// Generate random scrambles.
+    ///
+    /// Takes "moves" (default: 25), "count" (default: 1) and an optional
+    /// "seed".
+    fn scramble(&self, body: &Value) -> Result<Value, ApiError> {
+        let moves = get_u64(body, "moves", 25)?;
+        if moves > MAX_MOVES {
+            return Err(ApiError::bad_request(&format!("moves must be at most {}", MAX_MOVES)));
+        }
+        let count = get_u64(body, "count", 1)?;
+        if count > MAX_COUNT {
+            return Err(ApiError::bad_request(&format!("count must be at most {}", MAX_COUNT)));
+        }
+        let seed = get_u64(body, "seed", random())?;
+        let mut rng = seeded_rng(seed);
+        let scrambles: Vec<Value> = (0..count).map(|_| {
+            let scramble = scramble_with_rng(moves as usize, &mut rng);
+            let mut res = algo_to_json(&scramble);
+            res["state"] = state_to_json(&scramble.state());
+            res
+        }).collect();
+        Ok(json!({"seed": seed, "scrambles": scrambles}))
+    }
+
+    /// Find an optimal solution, giving up after the time limit.
+    fn optimal(&self, body: &Value) -> Result<Value, ApiError> {
+        let state = input_state(body)?;
+        let limit = self.time_limit(body)?;
+        let start_time = Instant::now();
+        let (cancel, done_tx) = cancel_after(limit);
+        let heuristic = CancelHeuristic::new(&self.heuristic, &cancel);
+        let min_depth = self.heuristic.lower_bound(&state, LockProj::project(&state));
+        let mut result = None;
+        for depth in min_depth..255 {
+            result = solve(&state, &heuristic, MoveSet::all(), Metric::FaceTurn, depth);
+            if result.is_some() || cancel.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+        drop(done_tx);
+        match result {
+            Some(solution) => Ok(json!({
+                "input": state_to_json(&state),
+                "solution": algo_to_json(&solution),
+                "ms": millis(start_time.elapsed())
+            })),
+            None => Err(ApiError::timeout(limit))
+        }
+    }
+
+    /// Find a multi-step solution, with a beam search if "beam_width" is
+    /// given.
+    ///
+    /// The beam search falls back to a greedy search at the time limit.
+    /// If even that does not finish in time, the solve is cancelled and the
+    /// request fails.
+    fn multi_step(&self, body: &Value) -> Result<Value, ApiError> {
+        let state = input_state(body)?;
+        let limit = self.time_limit(body)?;
+        let width = get_u64(body, "beam_width", 1)? as usize;
+        if width == 0 {
+            return Err(ApiError::bad_request("beam_width must be at least 1"));
+        }
+        let start_time = Instant::now();
+        let (cancel, done_tx) = cancel_after(limit);
+        let result = self.multi.solve_beam_named(&state, width, limit, &cancel);
+        drop(done_tx);
+        let (solution, parts) = match result {
+            Ok(result) => result,
+            Err(MultiStepError::Cancelled{..}) => return Err(ApiError::timeout(limit)),
+            Err(e) => return Err(ApiError{status: 422, error: error_to_json(&e)})
+        };
+
+        let mut parts_json = Vec::new();
+        let mut part_state = state.clone();
+        for (goal, part) in parts {
+            part.apply(&mut part_state);
+            let mut entry = algo_to_json(&part);
+            entry["goal"] = json!(goal);
+            entry["state"] = state_to_json(&part_state);
+            parts_json.push(entry);
+        }
+        Ok(json!({
+            "input": state_to_json(&state),
+            "solution": algo_to_json(&solution),
+            "parts": parts_json,
+            "ms": millis(start_time.elapsed())
+        }))
+    }
+
+    /// Check that a state is valid and, if a "solution" is given, that it
+    /// reaches the "goal" (default: solved) with legal moves.
+    ///
+    /// Invalid input is reported with "valid": false rather than as an
+    /// error.
+    fn validate(&self, body: &Value) -> Result<Value, ApiError> {
+        let state = match input_state(body) {
+            Ok(state) => state,
+            Err(e) => return Ok(json!({"valid": false, "error": e.error}))
+        };
+        let solution = match get_str(body, "solution")? {
+            Some(solution) => solution,
+            None => return Ok(json!({"valid": true, "state": state_to_json(&state)}))
+        };
+        let solution: Algo = solution.parse()
+            .map_err(|e| ApiError::bad_request(&format!("parse solution: {}", e)))?;
+        let goal: Goal = match get_str(body, "goal")? {
+            Some(goal) => goal.parse().map_err(|e: String| ApiError::bad_request(&e))?,
+            None => Goal::solved()
+        };
+        Ok(match verify(&state, &solution, &goal) {
+            Ok(end) => json!({"valid": true, "state": state_to_json(&end)}),
+            Err(e) => json!({"valid": false, "error": verify_error_to_json(&e)})
+        })
+    }
+
+    /// Draw a state as an SVG image, or a strip of frames if "algo" is
+    /// given.
+    fn render(&self, body: &Value) -> Result<String, ApiError> {
+        let state = if body.get("scramble").is_some() || body.get("state").is_some() {
+            input_state(body)?
+        } else {
+            State::default()
+        };
+        let view: SvgView = get_str(body, "view")?.unwrap_or("net").parse()
+            .map_err(|e: String| ApiError::bad_request(&e))?;
+        Ok(match get_str(body, "algo")? {
+            Some(algo) => {
+                let algo: Algo = algo.parse()
+                    .map_err(|e| ApiError::bad_request(&format!("parse algo: {}", e)))?;
+                render_algo_svg(&state, &algo, view)
+            },
+            None => render_svg(&state, view)
+        })
+    }
+
+    /// Get the time limit of a request from "time_limit_ms", or the default.
+    fn time_limit(&self, body: &Value) -> Result<Duration, ApiError> {
+        match body.get("time_limit_ms") {
+            None => Ok(self.time_limit),
+            Some(_) => Ok(Duration::from_millis(get_u64(body, "time_limit_ms", 0)?))
+        }
+    }
+}
+
+fn parse_body(request: &Request) -> Result<Value, ApiError> {
+    if request.body.is_empty() {
+        return Ok(json!({}));
+    }
+    let body: Value = from_slice(&request.body)
+        .map_err(|e| ApiError::bad_request(&format!("parse body: {}", e)))?;
+    if !body.is_object() {
+        return Err(ApiError::bad_request("body must be a JSON object"));
+    }
+    Ok(body)
+}
+
+/// Get the state of a request from either a legal "scramble" or "state".
+fn input_state(body: &Value) -> Result<State, ApiError> {
+    match (get_str(body, "scramble")?, get_str(body, "state")?) {
+        (Some(scramble), None) => {
+            let algo: Algo = scramble.parse()
+                .map_err(|e| ApiError::bad_request(&format!("parse scramble: {}", e)))?;
+            algo.legal_state()
+                .map_err(|e| ApiError::bad_request(&format!("illegal scramble: {}", e)))
+        },
+        (None, Some(state)) => {
+            state.parse().map_err(|e| ApiError::bad_request(&format!("parse state: {}", e)))
+        },
+        (Some(_), Some(_)) => {
+            Err(ApiError::bad_request("scramble and state cannot be used together"))
+        },
+        (None, None) => Err(ApiError::bad_request("either scramble or state is required"))
+    }
+}
+
+fn get_str<'a>(body: &'a Value, name: &str) -> Result<Option<&'a str>, ApiError> {
+    match body.get(name) {
+        None | Some(&Value::Null) => Ok(None),
+        Some(value) => value.as_str().map(Some)
+            .ok_or_else(|| ApiError::bad_request(&format!("{} must be a string", name)))
+    }
+}
+
+fn get_u64(body: &Value, name: &str, default: u64) -> Result<u64, ApiError> {
+    match body.get(name) {
+        None | Some(&Value::Null) => Ok(default),
+        Some(value) => value.as_u64().ok_or_else(|| {
+            ApiError::bad_request(&format!("{} must be a non-negative integer", name))
+        })
+    }
+}
+
+/// Cancel a search from another thread once the time limit passes, unless
+/// the search finishes first and drops the returned sender.
+fn cancel_after(limit: Duration) -> (Arc<AtomicBool>, Sender<()>) {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (done_tx, done_rx) = channel::<()>();
+    let timer_cancel = cancel.clone();
+    spawn(move || {
+        if let Err(RecvTimeoutError::Timeout) = done_rx.recv_timeout(limit) {
+            timer_cancel.store(true, Ordering::SeqCst);
+        }
+    });
+    (cancel, done_tx)
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + duration.subsec_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use locky_puzzle::{NopHeuristic, SolveStep};
+    use serde_json::from_str;
+
+    fn test_api() -> Api {
+        let nop: Arc<Heuristic> = Arc::new(NopHeuristic());
+        Api{
+            heuristic: MaxHeuristic(vec![Box::new(NopHeuristic())]),
+            multi: Arc::new(MultiStep::new(vec![Box::new(SolveStep::new(nop, 5))])),
+            time_limit: Duration::from_secs(10)
+        }
+    }
+
+    fn request(api: &Api, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let response = api.handle(&Request{
+            method: method.to_owned(),
+            path: path.to_owned(),
+            body: body.as_bytes().to_vec()
+        });
+        (response.status, from_str(&response.body).unwrap_or(Value::Null))
+    }
+
+    /// Test that requests reach their endpoints, or fail with 404 or 405.
+    #[test]
+    fn routing() {
+        let api = test_api();
+        assert_eq!(request(&api, "GET", "/health", ""), (200, json!({"status": "ok"})));
+        assert_eq!(request(&api, "OPTIONS", "/scramble", "").0, 204);
+        assert_eq!(request(&api, "GET", "/scramble", "").1["error"]["kind"],
+            "method_not_allowed");
+        assert_eq!(request(&api, "POST", "/health", "").0, 405);
+        assert_eq!(request(&api, "POST", "/nope", "").1["error"]["kind"], "not_found");
+
+        let (status, body) = request(&api, "POST", "/scramble", r#"{"moves": 7, "seed": 1}"#);
+        assert_eq!(status, 200);
+        assert_eq!(body["seed"], 1);
+        assert_eq!(body["scrambles"][0]["length"], 7);
+
+        let (status, body) = request(&api, "POST", "/solve/optimal", r#"{"scramble": "B D2"}"#);
+        assert_eq!((status, body["solution"]["length"].clone()), (200, json!(2)));
+        let (status, body) = request(&api, "POST", "/solve/multi-step",
+            r#"{"scramble": "B D2", "beam_width": 2}"#);
+        assert_eq!((status, body["solution"]["length"].clone()), (200, json!(2)));
+        let (status, body) = request(&api, "POST", "/validate",
+            r#"{"scramble": "F", "solution": "R"}"#);
+        assert_eq!((status, body["error"]["kind"].clone()), (200, json!("illegal_move")));
+    }
+
+    /// Test that bad bodies and arguments fail with 400.
+    #[test]
+    fn bad_requests() {
+        let api = test_api();
+        let bad = |path: &str, body: &str| {
+            let (status, body) = request(&api, "POST", path, body);
+            assert_eq!((status, body["error"]["kind"].clone()), (400, json!("bad_request")));
+            body["error"]["message"].as_str().unwrap().to_owned()
+        };
+        assert!(bad("/scramble", "{").starts_with("parse body"));
+        assert_eq!(bad("/scramble", "[1]"), "body must be a JSON object");
+        assert_eq!(bad("/scramble", r#"{"moves": -1}"#), "moves must be a non-negative integer");
+        assert_eq!(bad("/scramble", r#"{"moves": 1001}"#), "moves must be at most 1000");
+        assert_eq!(bad("/scramble", r#"{"count": 1001}"#), "count must be at most 1000");
+        assert_eq!(bad("/solve/optimal", "{}"), "either scramble or state is required");
+        assert_eq!(bad("/solve/optimal", r#"{"scramble": "R", "state": "x"}"#),
+            "scramble and state cannot be used together");
+        assert!(bad("/solve/optimal", r#"{"scramble": "R3"}"#).starts_with("parse scramble"));
+        assert_eq!(bad("/solve/multi-step", r#"{"scramble": "R", "beam_width": 0}"#),
+            "beam_width must be at least 1");
+        assert!(bad("/render", r#"{"view": "nope"}"#).len() > 0);
+    }
+}
@@ -0,0 +1,192 @@
+//! A minimal HTTP/1.1 implementation, just enough for JSON requests from a
+//! local web UI.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+use serde_json::Value;
+
+/// The largest request body that will be read.
+const MAX_BODY: usize = 1 << 20;
+
+/// The most header lines that will be read.
+const MAX_HEADERS: usize = 100;
+
+/// A parsed HTTP request.
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>
+}
+
+/// An HTTP response, which is always sent with "Connection: close".
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String
+}
+
+impl Response {
+    pub fn json(status: u16, body: &Value) -> Response {
+        Response{status: status, content_type: "application/json", body: body.to_string()}
+    }
+
+    pub fn svg(body: String) -> Response {
+        Response{status: 200, content_type: "image/svg+xml", body: body}
+    }
+}
+
+/// Read a request from a connection.
+///
+/// The query string, if any, is dropped from the path.
+pub fn read_request(stream: &TcpStream) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| format!("read request: {}", e))?;
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() != 3 || !parts[2].starts_with("HTTP/") {
+        return Err(format!("bad request line: {}", line.trim()));
+    }
+    let method = parts[0].to_owned();
+    let path = parts[1].split('?').next().unwrap().to_owned();
+
+    let mut content_length = 0;
+    for i in 0.. {
+        if i == MAX_HEADERS {
+            return Err("too many headers".to_owned());
+        }
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| format!("read header: {}", e))?;
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        let mut name_value = header.splitn(2, ':');
+        let name = name_value.next().unwrap().trim();
+        let value = name_value.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse()
+                .map_err(|_| format!("bad Content-Length: {}", value))?;
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(format!("body is larger than {} bytes", MAX_BODY));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|e| format!("read body: {}", e))?;
+    Ok(Request{method: method, path: path, body: body})
+}
+
+/// Write a response to a connection.
+///
+/// Every response allows cross-origin requests, since the web UI may be
+/// served from a different port.
+pub fn write_response(mut stream: &TcpStream, response: &Response) -> Result<(), String> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: {}\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\r\n",
+        response.status, reason(response.status), response.content_type, response.body.len());
+    stream.write_all(head.as_bytes())
+        .and_then(|_| stream.write_all(response.body.as_bytes()))
+        .map_err(|e| format!("write response: {}", e))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::from_str;
+    use std::net::{Shutdown, TcpListener};
+    use std::thread;
+
+    /// Send raw bytes to a local server that handles one request, and return
+    /// the parsed request along with the raw response.
+    fn serve_one(raw: Vec<u8>) -> (Result<Request, String>, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            let _ = stream.write_all(&raw);
+            let _ = stream.shutdown(Shutdown::Write);
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response);
+            response
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let request = read_request(&stream);
+        if let Ok(ref request) = request {
+            let body = json!({
+                "method": request.method,
+                "path": request.path,
+                "body": String::from_utf8_lossy(&request.body)
+            });
+            write_response(&stream, &Response::json(200, &body)).unwrap();
+        }
+        drop(stream);
+        (request, client.join().unwrap())
+    }
+
+    /// Test reading a request and writing a response over a socket.
+    #[test]
+    fn round_trip() {
+        let raw = "POST /solve?x=1 HTTP/1.1\r\nHost: localhost\r\n\
+                   content-length: 13\r\n\r\n{\"state\": 1}\n";
+        let (request, response) = serve_one(raw.as_bytes().to_vec());
+        let request = request.unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/solve");
+        assert_eq!(request.body, b"{\"state\": 1}\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/json\r\n"));
+        assert!(response.contains("Connection: close\r\n"));
+        let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        assert!(response.contains(&format!("Content-Length: {}\r\n", body.len())));
+        let body: Value = from_str(body).unwrap();
+        assert_eq!(body["path"], "/solve");
+        assert_eq!(body["body"], "{\"state\": 1}\n");
+
+        let (request, _) = serve_one(b"GET / HTTP/1.1\r\n\r\n".to_vec());
+        assert!(request.unwrap().body.is_empty());
+    }
+
+    /// Test that malformed or oversized requests are rejected.
+    #[test]
+    fn bad_requests() {
+        let (request, response) = serve_one(b"GET /\r\n\r\n".to_vec());
+        assert!(request.is_err());
+        assert!(response.is_empty());
+
+        let mut raw = "GET / HTTP/1.1\r\n".to_owned();
+        for i in 0..MAX_HEADERS {
+            raw.push_str(&format!("X-Header-{}: {}\r\n", i, i));
+        }
+        raw.push_str("\r\n");
+        assert_eq!(serve_one(raw.into_bytes()).0.err().unwrap(), "too many headers");
+
+        let raw = "POST / HTTP/1.1\r\nContent-Length: ten\r\n\r\n";
+        assert!(serve_one(raw.as_bytes().to_vec()).0.is_err());
+
+        let raw = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1);
+        assert!(serve_one(raw.into_bytes()).0.is_err());
+
+        let raw = "POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort";
+        assert!(serve_one(raw.as_bytes().to_vec()).0.is_err());
+    }
+}
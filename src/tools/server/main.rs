@@ -0,0 +1,120 @@
+//! A local HTTP server for solving the locky puzzle from other programs,
+//! such as a web UI.
+
+extern crate clap;
+extern crate locky_puzzle;
+extern crate rand;
+#[macro_use]
+extern crate serde_json;
+
+mod api;
+mod http;
+
+use std::net::TcpListener;
+use std::process::exit;
+use std::sync::Arc;
+use std::thread::spawn;
+use std::time::{Duration, Instant};
+
+use clap::{App, Arg, ArgMatches};
+use locky_puzzle::{ArrowAxisProj, CornerProj, Heuristic, MaxHeuristic, Metric, MoveSet,
+    MultiStep, ProjHeuristic};
+
+use api::Api;
+use http::{Response, read_request, write_response};
+
+fn main() {
+    let matches = App::new("locky-server")
+        .arg(Arg::with_name("host")
+            .long("host")
+            .value_name("ADDR")
+            .takes_value(true)
+            .help("Set the address to listen on (default: 127.0.0.1)"))
+        .arg(Arg::with_name("port")
+            .long("port")
+            .value_name("NUM")
+            .takes_value(true)
+            .help("Set the port to listen on (default: 8080)"))
+        .arg(Arg::with_name("depth")
+            .long("depth")
+            .value_name("NUM")
+            .takes_value(true)
+            .help("Set the depth of the optimal solver's heuristics (default: 7)"))
+        .arg(Arg::with_name("pipeline")
+            .long("pipeline")
+            .value_name("NAME")
+            .takes_value(true)
+            .help("Set the multi-step pipeline, default or corners-first (default: default)"))
+        .arg(Arg::with_name("time-limit")
+            .long("time-limit")
+            .value_name("SECS")
+            .takes_value(true)
+            .help("Set the default time limit of solve requests (default: 10)"))
+        .get_matches();
+    if let Err(e) = run(&matches) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let host = matches.value_of("host").unwrap_or("127.0.0.1");
+    let port: u16 = parse_arg("port", matches.value_of("port").unwrap_or("8080"))?;
+    let depth: u8 = parse_arg("depth", matches.value_of("depth").unwrap_or("7"))?;
+    let time_limit: u64 = parse_arg("time-limit", matches.value_of("time-limit").unwrap_or("10"))?;
+    let pipeline = matches.value_of("pipeline").unwrap_or("default").to_owned();
+    if pipeline != "default" && pipeline != "corners-first" {
+        return Err(format!("unknown pipeline: {}", pipeline));
+    }
+
+    // Bind first, so that a busy port is reported before the slow part.
+    let listener = TcpListener::bind((host, port))
+        .map_err(|e| format!("listen on {}:{}: {}", host, port, e))?;
+
+    println!("Generating tables...");
+    let start_time = Instant::now();
+    let multi_thread = spawn(move || match pipeline.as_str() {
+        "corners-first" => MultiStep::generate_corners_first(),
+        _ => MultiStep::generate_default()
+    });
+    let heuristics: Vec<Box<Heuristic>> = vec![
+        Box::new(ProjHeuristic::<CornerProj>::generate(depth, MoveSet::all(), Metric::FaceTurn)),
+        Box::new(ProjHeuristic::<ArrowAxisProj>::generate(depth, MoveSet::all(),
+            Metric::FaceTurn))
+    ];
+    let api = Arc::new(Api{
+        heuristic: MaxHeuristic(heuristics),
+        multi: Arc::new(multi_thread.join().unwrap()),
+        time_limit: Duration::from_secs(time_limit)
+    });
+    println!("Generated tables in {:.1}s", start_time.elapsed().as_secs_f64());
+
+    println!("Listening on http://{}:{}", host, port);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("accept: {}", e);
+                continue;
+            }
+        };
+        let api = api.clone();
+        spawn(move || {
+            stream.set_read_timeout(Some(Duration::from_secs(10))).ok();
+            let response = match read_request(&stream) {
+                Ok(request) => api.handle(&request),
+                Err(e) => Response::json(400, &json!({
+                    "error": {"kind": "bad_request", "message": e}
+                }))
+            };
+            if let Err(e) = write_response(&stream, &response) {
+                eprintln!("{}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn parse_arg<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("bad {} argument: {}", name, value))
+}
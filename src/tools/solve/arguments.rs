@@ -1,12 +1,52 @@
 //! Command-line arguments.
 
-use clap::{App, Arg};
+use std::thread::available_parallelism;
+
+use clap::{App, Arg, ArgMatches};
+
+use locky_puzzle::{Metric, MoveSet, TextStyle};
 
 /// The parsed command-line arguments.
 pub struct Args {
+    pub format: OutputFormat,
     pub heuristic: HeuristicArgs,
     pub scramble: Option<String>,
-    pub multi_step: bool
+    pub state: Option<String>,
+    pub state_file: Option<String>,
+    pub batch: Option<String>,
+    pub jobs: usize,
+    pub net: Option<TextStyle>,
+    pub verbose_steps: bool,
+    pub multi_step: bool,
+    pub two_phase: bool,
+    pub anytime: bool,
+    pub optimize: usize,
+    pub pipeline: String,
+    pub beam_width: usize,
+    pub time_limit: u64,
+    pub moves: MoveSet,
+    pub metric: Metric,
+    pub ranked: usize,
+    pub slack: u8
+}
+
+/// The way results are printed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// Human-readable text, printed as the solver makes progress.
+    Text,
+
+    /// A single JSON object, printed once the solver is done.
+    Json
+}
+
+/// Invalid command-line arguments.
+///
+/// This includes the output format, so that the error can be reported in
+/// the requested format.
+pub struct ArgsError {
+    pub format: OutputFormat,
+    pub message: String
 }
 
 /// Arguments that determine the search heuristic.
@@ -20,7 +60,7 @@ pub struct HeuristicArgs {
 }
 
 /// Parse the command-line arguments.
-pub fn parse_args() -> Result<Args, String> {
+pub fn parse_args() -> Result<Args, ArgsError> {
     let matches = App::new("locky-solve")
         .arg(Arg::with_name("corner-depth")
             .long("corner-depth")
@@ -52,10 +92,103 @@ pub fn parse_args() -> Result<Args, String> {
             .value_name("ALGO")
             .help("Set a specific algorithm to solve")
             .takes_value(true))
+        .arg(Arg::with_name("state")
+            .long("state")
+            .value_name("STATE")
+            .help("Set a specific state to solve, as facelets or as printed by the solver")
+            .takes_value(true))
+        .arg(Arg::with_name("state-file")
+            .long("state-file")
+            .value_name("FILE")
+            .help("Read the state to solve from FILE, or - for standard input")
+            .takes_value(true))
+        .arg(Arg::with_name("batch")
+            .long("batch")
+            .value_name("FILE")
+            .help("Solve each scramble or state in FILE, one per line, or - for standard input")
+            .takes_value(true))
+        .arg(Arg::with_name("jobs")
+            .long("jobs")
+            .value_name("NUM")
+            .help("Solve NUM batch lines at once (default: number of CPUs)")
+            .takes_value(true))
+        .arg(Arg::with_name("net")
+            .long("net")
+            .value_name("STYLE")
+            .help("Draw the input and each solution step, either in ascii or ansi colors")
+            .takes_value(true))
+        .arg(Arg::with_name("verbose-steps")
+            .long("verbose-steps")
+            .help("Print the state, the locked faces and the goal after each solution step"))
         .arg(Arg::with_name("multi-step")
             .long("multi-step")
             .help("Solve the puzzle in multiple steps"))
+        .arg(Arg::with_name("two-phase")
+            .long("two-phase")
+            .help("Solve the puzzle in two phases, improving the solution until the time limit"))
+        .arg(Arg::with_name("anytime")
+            .long("anytime")
+            .help("Print shorter and shorter solutions until the optimum or the time limit"))
+        .arg(Arg::with_name("pipeline")
+            .long("pipeline")
+            .value_name("NAME")
+            .help("Set the multi-step pipeline, either default or corners-first (default: default)")
+            .takes_value(true))
+        .arg(Arg::with_name("beam-width")
+            .long("beam-width")
+            .value_name("NUM")
+            .help("Keep NUM candidate solutions for each multi-step step (default: 1)")
+            .takes_value(true))
+        .arg(Arg::with_name("time-limit")
+            .long("time-limit")
+            .value_name("SECONDS")
+            .help("Stop improving solutions after SECONDS (default: 60)")
+            .takes_value(true))
+        .arg(Arg::with_name("optimize")
+            .long("optimize")
+            .value_name("NUM")
+            .help("Shorten multi-step or two-phase solutions by optimizing windows of NUM moves")
+            .takes_value(true))
+        .arg(Arg::with_name("moves")
+            .long("moves")
+            .value_name("MOVES")
+            .help("Restrict the search to a set of moves, e.g. R,U,F or R2,U (default: all)")
+            .takes_value(true))
+        .arg(Arg::with_name("metric")
+            .long("metric")
+            .value_name("METRIC")
+            .help("Set the metric to optimize, either ftm or qtm (default: ftm)")
+            .takes_value(true))
+        .arg(Arg::with_name("ranked")
+            .long("ranked")
+            .value_name("NUM")
+            .help("Print the NUM easiest solutions to execute by hand")
+            .takes_value(true))
+        .arg(Arg::with_name("slack")
+            .long("slack")
+            .value_name("NUM")
+            .help("Allow ranked solutions NUM moves longer than optimal (default: 0)")
+            .takes_value(true))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .value_name("FORMAT")
+            .help("Print results as text or as a json object (default: text)")
+            .takes_value(true))
         .get_matches();
+    let format = match matches.value_of("format").unwrap_or("text") {
+        "text" => OutputFormat::Text,
+        "json" => OutputFormat::Json,
+        f => return Err(ArgsError{
+            format: OutputFormat::Text,
+            message: format!("bad format argument: {}", f)
+        })
+    };
+    args_from_matches(&matches, format).map_err(|message| {
+        ArgsError{format: format, message: message}
+    })
+}
+
+fn args_from_matches(matches: &ArgMatches, format: OutputFormat) -> Result<Args, String> {
 
     macro_rules! parse_arg {
         ( $name:expr, $default:expr ) => {
@@ -64,7 +197,58 @@ pub fn parse_args() -> Result<Args, String> {
         }
     }
 
+    let moves: MoveSet = parse_arg!("moves", "U,D,F,B,R,L");
+    let metric = match matches.value_of("metric").unwrap_or("ftm") {
+        "ftm" | "htm" => Metric::FaceTurn,
+        "qtm" => Metric::QuarterTurn,
+        m => return Err(format!("bad metric argument: {}", m))
+    };
+    let inputs = ["scramble", "state", "state-file"];
+    if inputs.iter().filter(|i| matches.is_present(i)).count() > 1 {
+        return Err("--scramble, --state and --state-file cannot be used together".to_owned());
+    }
+    let modes = ["multi-step", "two-phase", "anytime"];
+    for mode in &modes {
+        for name in &["moves", "metric", "ranked", "slack"] {
+            if matches.is_present(mode) && matches.is_present(name) {
+                return Err(format!("--{} is not supported with --{}", name, mode));
+            }
+        }
+    }
+    if modes.iter().filter(|m| matches.is_present(m)).count() > 1 {
+        return Err("--multi-step, --two-phase and --anytime cannot be used together".to_owned());
+    }
+    if matches.is_present("batch") {
+        for name in &["scramble", "state", "state-file", "net", "verbose-steps", "anytime",
+                      "ranked", "optimize"] {
+            if matches.is_present(name) {
+                return Err(format!("--{} is not supported with --batch", name));
+            }
+        }
+    } else if matches.is_present("jobs") {
+        return Err("--jobs requires --batch".to_owned());
+    }
+    if format == OutputFormat::Json {
+        if !inputs.iter().any(|i| matches.is_present(i)) && !matches.is_present("batch") {
+            return Err("--format json requires --scramble, --state, --state-file or --batch"
+                .to_owned());
+        }
+        for name in &["net", "verbose-steps"] {
+            if matches.is_present(name) {
+                return Err(format!("--{} is not supported with --format json", name));
+            }
+        }
+    }
+    if matches.is_present("anytime") && matches.is_present("verbose-steps") {
+        return Err("--verbose-steps is not supported with --anytime".to_owned());
+    }
+    if matches.is_present("optimize") && !matches.is_present("multi-step") &&
+        !matches.is_present("two-phase") {
+        return Err("--optimize requires --multi-step or --two-phase".to_owned());
+    }
+
     Ok(Args{
+        format: format,
         heuristic: HeuristicArgs{
             corner_depth: parse_arg!("corner-depth", "0"),
             arrow_axis_depth: parse_arg!("arrow-axis-depth", "0"),
@@ -73,6 +257,32 @@ pub fn parse_args() -> Result<Args, String> {
             lock_depth: parse_arg!("lock-depth", "0")
         },
         scramble: matches.value_of("scramble").map(From::from),
-        multi_step: matches.is_present("multi-step")
+        state: matches.value_of("state").map(From::from),
+        state_file: matches.value_of("state-file").map(From::from),
+        batch: matches.value_of("batch").map(From::from),
+        jobs: match matches.value_of("jobs") {
+            Some(jobs) => match jobs.parse() {
+                Ok(0) => return Err("--jobs must be at least 1".to_owned()),
+                Ok(jobs) => jobs,
+                Err(e) => return Err(format!("bad jobs argument: {}", e))
+            },
+            None => available_parallelism().map(|n| n.get()).unwrap_or(1)
+        },
+        net: match matches.value_of("net") {
+            Some(style) => Some(style.parse().map_err(|e| format!("bad net argument: {}", e))?),
+            None => None
+        },
+        verbose_steps: matches.is_present("verbose-steps"),
+        multi_step: matches.is_present("multi-step"),
+        two_phase: matches.is_present("two-phase"),
+        anytime: matches.is_present("anytime"),
+        optimize: parse_arg!("optimize", "0"),
+        pipeline: matches.value_of("pipeline").unwrap_or("default").to_owned(),
+        beam_width: parse_arg!("beam-width", "1"),
+        time_limit: parse_arg!("time-limit", "60"),
+        moves: moves,
+        metric: metric,
+        ranked: parse_arg!("ranked", "0"),
+        slack: parse_arg!("slack", "0")
     })
 }
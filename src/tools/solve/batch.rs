@@ -0,0 +1,241 @@
+//! Solving many scrambles or states, one per line of input.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, stdin};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::thread::spawn;
+use std::time::{Duration, Instant};
+
+use locky_puzzle::{Algo, Heuristic, MaxHeuristic, Metric, MoveSet, MultiStep, Phase1Proj, State,
+    TwoPhase, algo_to_json, solve, state_to_json};
+use serde_json::Value;
+
+use arguments::{Args, OutputFormat};
+use heuristic::make_heuristic;
+use {Failure, Report, failure_to_json, millis};
+
+/// A solver that is generated once and shared by every line.
+enum Solver {
+    Optimal(MaxHeuristic<Box<Heuristic>>, MoveSet, Metric),
+    MultiStep(MultiStep, usize, Duration),
+    TwoPhase(TwoPhase<Phase1Proj>, Duration)
+}
+
+impl Solver {
+    fn solve(&self, s: &State) -> Result<Algo, Failure> {
+        match self {
+            &Solver::Optimal(ref heuristic, moves, metric) => {
+                for depth in 0..255 {
+                    if let Some(solution) = solve(s, heuristic, moves, metric, depth) {
+                        return Ok(solution);
+                    }
+                }
+                Err(Failure::Message("no solution within 254 moves".to_owned()))
+            },
+            &Solver::MultiStep(ref multi, 1, _) => {
+                multi.solve(s).map(|(solution, _)| solution).map_err(Failure::Solve)
+            },
+            &Solver::MultiStep(ref multi, beam_width, time_limit) => {
+                multi.solve_beam(s, beam_width, time_limit, &AtomicBool::new(false))
+                    .map(|(solution, _)| solution)
+                    .map_err(Failure::Solve)
+            },
+            &Solver::TwoPhase(ref two_phase, time_limit) => {
+                two_phase.solve(s, time_limit)
+                    .map(|(solution, _)| solution)
+                    .map_err(Failure::Solve)
+            }
+        }
+    }
+}
+
+/// The outcome of solving one line.
+struct LineResult {
+    line: usize,
+    input: String,
+    state: Option<State>,
+    result: Result<Algo, Failure>,
+    millis: u64
+}
+
+/// Solve every line of the batch file in parallel, printing the results in
+/// order followed by a summary.
+pub fn run_batch(args: &Args, path: &str, report: &mut Report) -> Result<(), Failure> {
+    let lines = read_lines(path)?;
+    let time_limit = Duration::from_secs(args.time_limit);
+    say!(args, "Generating solver...");
+    let start_time = Instant::now();
+    let solver = Arc::new(if args.multi_step {
+        let multi = match args.pipeline.as_str() {
+            "default" => MultiStep::generate_default(),
+            "corners-first" => MultiStep::generate_corners_first(),
+            name => return Err(Failure::Message(format!("unknown pipeline: {}", name)))
+        };
+        Solver::MultiStep(multi, args.beam_width, time_limit)
+    } else if args.two_phase {
+        Solver::TwoPhase(TwoPhase::generate_default(), time_limit)
+    } else {
+        let heuristic = make_heuristic(&args.heuristic, args.moves, args.metric).recv().unwrap();
+        Solver::Optimal(heuristic, args.moves, args.metric)
+    });
+    report.insert("generate_ms".to_owned(), json!(millis(start_time)));
+    say!(args, "Solving {} lines with {} jobs...", lines.len(), args.jobs);
+
+    let solve_time = Instant::now();
+    let lines = Arc::new(lines);
+    let next_line = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = channel();
+    for _ in 0..args.jobs {
+        let (lines, next_line, solver, tx) = (lines.clone(), next_line.clone(), solver.clone(),
+            tx.clone());
+        spawn(move || {
+            loop {
+                let i = next_line.fetch_add(1, Ordering::SeqCst);
+                if i >= lines.len() {
+                    return;
+                }
+                let (line, ref input) = lines[i];
+                let line_time = Instant::now();
+                let state = parse_line(input);
+                let result = match state {
+                    Ok(ref state) => solver.solve(state),
+                    Err(ref e) => Err(Failure::Message(e.clone()))
+                };
+                tx.send((i, LineResult{
+                    line: line,
+                    input: input.clone(),
+                    state: state.ok(),
+                    result: result,
+                    millis: millis(line_time)
+                })).unwrap();
+            }
+        });
+    }
+    drop(tx);
+
+    // Print results in input order, even though they finish out of order.
+    let mut pending = BTreeMap::new();
+    let mut next_result = 0;
+    let mut results = Vec::new();
+    let mut lengths = Vec::new();
+    let mut times = Vec::new();
+    let mut failed = 0;
+    for (i, result) in rx {
+        pending.insert(i, result);
+        while let Some(result) = pending.remove(&next_result) {
+            next_result += 1;
+            match result.result {
+                Ok(ref solution) => {
+                    let length = solution.cost(args.metric);
+                    say!(args, "{}: {} ({} moves, {} ms)", result.line, solution, length,
+                        result.millis);
+                    lengths.push(length as u64);
+                    times.push(result.millis);
+                },
+                Err(ref e) => {
+                    say!(args, "{}: error: {}", result.line, e);
+                    failed += 1;
+                }
+            }
+            results.push(result_to_json(&result));
+        }
+    }
+
+    let lengths = Stats::new(&mut lengths);
+    let times = Stats::new(&mut times);
+    let total = millis(solve_time);
+    say!(args, "Solved {} of {} lines in {} ms", results.len() - failed, results.len(), total);
+    if let (&Some(ref lengths), &Some(ref times)) = (&lengths, &times) {
+        say!(args, "Length: mean {:.1}, median {:.1}, max {}", lengths.mean, lengths.median,
+            lengths.max);
+        say!(args, "Time (ms): mean {:.1}, median {:.1}, max {}", times.mean, times.median,
+            times.max);
+    }
+    let summary = json!({
+        "lines": results.len(),
+        "solved": results.len() - failed,
+        "failed": failed,
+        "length": lengths.map(|s| s.to_json()),
+        "ms": times.map(|s| s.to_json()),
+        "total_ms": total
+    });
+    report.insert("mode".to_owned(), json!("batch"));
+    report.insert("results".to_owned(), json!(results));
+    report.insert("summary".to_owned(), summary);
+    Ok(())
+}
+
+/// Read the non-empty lines of a file (or stdin for "-"), along with their
+/// line numbers, skipping comments that start with '#'.
+fn read_lines(path: &str) -> Result<Vec<(usize, String)>, String> {
+    let reader: Box<Read> = if path == "-" {
+        Box::new(stdin())
+    } else {
+        Box::new(File::open(path).map_err(|e| format!("open {}: {}", path, e))?)
+    };
+    let mut res = Vec::new();
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line.map_err(|e| format!("read {}: {}", path, e))?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            res.push((i + 1, line.to_owned()));
+        }
+    }
+    Ok(res)
+}
+
+/// Parse a line as a state (in facelet or display format) or a legal
+/// scramble.
+fn parse_line(line: &str) -> Result<State, String> {
+    if line.starts_with('[') || line.contains('^') {
+        return line.parse().map_err(|e| format!("parse state: {}", e));
+    }
+    let algo: Algo = line.parse().map_err(|e| format!("parse scramble: {}", e))?;
+    algo.legal_state().map_err(|e| format!("illegal scramble: {}", e))
+}
+
+fn result_to_json(result: &LineResult) -> Value {
+    let mut res = json!({
+        "line": result.line,
+        "input": result.input,
+        "ms": result.millis
+    });
+    if let Some(ref state) = result.state {
+        res["state"] = state_to_json(state);
+    }
+    match result.result {
+        Ok(ref solution) => res["solution"] = algo_to_json(solution),
+        Err(ref e) => res["error"] = failure_to_json(e)
+    }
+    res
+}
+
+/// Summary statistics of some measurements.
+struct Stats {
+    mean: f64,
+    median: f64,
+    max: u64
+}
+
+impl Stats {
+    /// Compute the statistics of some values, or None if there are none.
+    fn new(values: &mut [u64]) -> Option<Stats> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort();
+        let n = values.len();
+        Some(Stats{
+            mean: values.iter().sum::<u64>() as f64 / n as f64,
+            median: (values[(n - 1) / 2] + values[n / 2]) as f64 / 2.0,
+            max: values[n - 1]
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        json!({"mean": self.mean, "median": self.median, "max": self.max})
+    }
+}
@@ -1,36 +1,49 @@
 //! Create heuristics as specified by the user.
 
 use std::mem::drop;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::{Receiver, Sender, channel};
 use std::thread::spawn;
 
 use locky_puzzle::{ArrowAxisProj, CornerFbProj, CornerProj, CornerRlProj, CornerUdProj, CoFbProj,
-    CoRlProj, CoUdProj, Heuristic, LockProj, MaxHeuristic, Proj, ProjHeuristic};
+    CoRlProj, CoUdProj, Heuristic, LockProj, MaxHeuristic, Metric, MoveSet, Proj, ProjHeuristic,
+    State};
 use arguments::HeuristicArgs;
 
 /// Generate the aggregate heuristic from the arguments.
 ///
+/// The tables only explore the given moves, and measure distances in the
+/// given metric.
 /// The computation is done asynchronously.
-pub fn make_heuristic(args: &HeuristicArgs) -> Receiver<MaxHeuristic<Box<Heuristic>>> {
+pub fn make_heuristic(
+    args: &HeuristicArgs,
+    moves: MoveSet,
+    metric: Metric
+) -> Receiver<MaxHeuristic<Box<Heuristic>>> {
     let (send_individual, recv_individual) = channel();
+    macro_rules! start_heuristic {
+        ( $proj:ident, $depth:expr ) => {
+            make_proj_heuristic::<$proj>($depth, moves, metric, send_individual.clone());
+        }
+    }
     if args.corner_depth > 0 {
-        make_proj_heuristic::<CornerProj>(args.corner_depth, send_individual.clone());
+        start_heuristic!(CornerProj, args.corner_depth);
     }
     if args.arrow_axis_depth > 0 {
-        make_proj_heuristic::<ArrowAxisProj>(args.arrow_axis_depth, send_individual.clone());
+        start_heuristic!(ArrowAxisProj, args.arrow_axis_depth);
     }
     if args.co_depth > 0 {
-        make_proj_heuristic::<CoFbProj>(args.co_depth, send_individual.clone());
-        make_proj_heuristic::<CoRlProj>(args.co_depth, send_individual.clone());
-        make_proj_heuristic::<CoUdProj>(args.co_depth, send_individual.clone());
+        start_heuristic!(CoFbProj, args.co_depth);
+        start_heuristic!(CoRlProj, args.co_depth);
+        start_heuristic!(CoUdProj, args.co_depth);
     }
     if args.corner_axis_depth > 0 {
-        make_proj_heuristic::<CornerFbProj>(args.corner_axis_depth, send_individual.clone());
-        make_proj_heuristic::<CornerRlProj>(args.corner_axis_depth, send_individual.clone());
-        make_proj_heuristic::<CornerUdProj>(args.corner_axis_depth, send_individual.clone());
+        start_heuristic!(CornerFbProj, args.corner_axis_depth);
+        start_heuristic!(CornerRlProj, args.corner_axis_depth);
+        start_heuristic!(CornerUdProj, args.corner_axis_depth);
     }
     if args.lock_depth > 0 {
-        make_proj_heuristic::<LockProj>(args.lock_depth, send_individual.clone());
+        start_heuristic!(LockProj, args.lock_depth);
     }
     drop(send_individual);
 
@@ -42,8 +55,38 @@ pub fn make_heuristic(args: &HeuristicArgs) -> Receiver<MaxHeuristic<Box<Heurist
     recv_agg
 }
 
-fn make_proj_heuristic<P: Proj + 'static>(depth: u8, sender: Sender<Box<Heuristic>>) {
+fn make_proj_heuristic<P: Proj + 'static>(
+    depth: u8,
+    moves: MoveSet,
+    metric: Metric,
+    sender: Sender<Box<Heuristic>>
+) {
     spawn(move || {
-        sender.send(Box::new(ProjHeuristic::<P>::generate(depth))).unwrap();
+        sender.send(Box::new(ProjHeuristic::<P>::generate(depth, moves, metric))).unwrap();
     });
 }
+
+/// A heuristic that counts how many times it is evaluated, which is roughly
+/// the number of nodes visited by a search.
+pub struct CountingHeuristic<'a, H: Heuristic + 'a> {
+    inner: &'a H,
+    nodes: AtomicUsize
+}
+
+impl<'a, H: Heuristic> CountingHeuristic<'a, H> {
+    pub fn new(inner: &'a H) -> CountingHeuristic<'a, H> {
+        CountingHeuristic{inner: inner, nodes: AtomicUsize::new(0)}
+    }
+
+    /// Get the number of evaluations since the last call.
+    pub fn take_nodes(&self) -> usize {
+        self.nodes.swap(0, Ordering::Relaxed)
+    }
+}
+
+impl<'a, H: Heuristic> Heuristic for CountingHeuristic<'a, H> {
+    fn lower_bound(&self, s: &State, l: LockProj) -> u8 {
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+        self.inner.lower_bound(s, l)
+    }
+}
@@ -1,19 +1,39 @@
 //! Getting the state of the cube from the user.
 
-use std::io::{Write, stdin, stdout};
+use std::fs::File;
+use std::io::{BufRead, Read, Write, stdin, stdout};
 
-use locky_puzzle::{Algo, Direction, State, Sticker};
+use locky_puzzle::{Algo, State};
 use arguments::Args;
 
 pub fn read_state(args: &Args) -> Result<State, String> {
     if let &Some(ref scramble) = &args.scramble {
         let algo: Algo = scramble.parse().map_err(|e| format!("parse scramble: {}", e))?;
-        Ok(algo.state())
+        algo.legal_state().map_err(|e| format!("illegal scramble: {}", e))
+    } else if let &Some(ref state) = &args.state {
+        state.parse().map_err(|e| format!("parse state: {}", e))
+    } else if let &Some(ref path) = &args.state_file {
+        read_state_file(path)
     } else {
         read_stdin()
     }
 }
 
+/// Read a state from a file (or stdin for "-") without prompting.
+///
+/// The file holds all six faces in the format of the interactive prompt,
+/// separated by whitespace or newlines, or a state as printed by the solver.
+fn read_state_file(path: &str) -> Result<State, String> {
+    let mut contents = String::new();
+    if path == "-" {
+        stdin().read_to_string(&mut contents).map_err(|e| format!("read stdin: {}", e))?;
+    } else {
+        File::open(path).and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|e| format!("read {}: {}", path, e))?;
+    }
+    contents.parse().map_err(|e| format!("parse state file: {}", e))
+}
+
 fn read_stdin() -> Result<State, String> {
     println!("Enter each face of the scramble. Read each face from the top left");
     println!("to the bottom right, where you view the B face using y2, and when");
@@ -21,7 +41,7 @@ fn read_stdin() -> Result<State, String> {
     println!("situation where U is on top and F is in front.");
     println!("");
     println!("When a sticker has an arrow, put the '^' character after it.");
-    println!("For example, the top face of a solved cube is UU^UUUUU^U.");
+    println!("For example, the top face of a solved cube is UU^UUUUUU^U.");
     println!("");
     println!("Color scheme:");
     println!("  U - yellow");
@@ -30,84 +50,60 @@ fn read_stdin() -> Result<State, String> {
     println!("  B - green");
     println!("  R - black");
     println!("  L - white");
-    let mut state = State::default();
+    let stdin = stdin();
+    let mut input = stdin.lock();
+    read_prompts(&mut input)
+}
+
+/// Prompt for each face and read it from a line of input.
+///
+/// Each line must hold exactly one face, which is checked before prompting
+/// for the next one.
+fn read_prompts<R: BufRead>(input: &mut R) -> Result<State, String> {
     use locky_puzzle::Face::*;
+    let mut state = State::default();
     for face in &[U, D, F, B, R, L] {
         print!("Enter {} face: ", face);
         stdout().flush().unwrap();
-        let row = read_sticker_row()?;
-        if &row[4].face != face || row[4].direction != Direction::Neutral {
-            return Err("invalid center sticker".to_owned());
-        }
-        let face_dst = state.face_mut(*face);
-        for i in 0..4 {
-            face_dst[i] = row[i];
-        }
-        for i in 4..8 {
-            face_dst[i] = row[i + 1];
-        }
+        let mut line = String::new();
+        input.read_line(&mut line).map_err(|e| format!("failed to read face: {}", e))?;
+        state.set_face_str(*face, &line).map_err(|e| format!("parse {} face: {}", face, e))?;
     }
-    validate_state(&state)?;
+    state.validate().map_err(|e| format!("parse state: {}", e))?;
     Ok(state)
 }
 
-fn read_sticker_row() -> Result<[Sticker; 9], String> {
-    // TODO: why does path need to be qualified?
-    use locky_puzzle::Face::*;
-    use locky_puzzle::Direction::*;
-
-    let mut buf = String::new();
-    stdin().read_line(&mut buf).map_err(|e| format!("failed to read face: {}", e))?;
-    let line = buf.trim();
-    if line.len() < 9 {
-        return Err("line not long enough".to_owned());
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut result = [Sticker{face: U, direction: Neutral}; 9];
-    let mut idx = 0;
-    for ch in line.chars() {
-        if ch == '^' {
-            if idx != 2 && idx != 4 && idx != 6 && idx != 8 {
-                return Err("invalid placement of '^' (must be after an edge)".to_owned());
-            } else {
-                result[idx - 1].direction = result[idx - 1].face.standard_direction()
+    /// Test that a six-line state file parses to the same state as the
+    /// prompts.
+    #[test]
+    fn prompt_format() {
+        let state = "B D2 B' U2 L2".parse::<Algo>().unwrap().state();
+        let mut file = String::new();
+        let mut stickers = 0;
+        for ch in state.to_facelet_string().chars() {
+            if ch != '^' {
+                if stickers > 0 && stickers % 9 == 0 {
+                    file.push('\n');
+                }
+                stickers += 1;
             }
-        } else if idx == 9 {
-            return Err(format!("excess character: {}", ch));
-        } else {
-            result[idx].face = match ch {
-                'U' => U,
-                'D' => D,
-                'F' => F,
-                'B' => B,
-                'R' => R,
-                'L' => L,
-                _ => return Err(format!("invalid character: {}", ch))
-            };
-            idx += 1;
+            file.push(ch);
         }
-    }
-    if idx < 9 {
-        Err("not enough characters".to_owned())
-    } else {
-        Ok(result)
-    }
-}
+        assert_eq!(file.lines().count(), 6);
+        assert_eq!(file.parse::<State>().unwrap(), state);
+        assert_eq!(read_prompts(&mut file.as_bytes()).unwrap(), state);
 
-fn validate_state(state: &State) -> Result<(), String> {
-    use locky_puzzle::Direction::*;
-    let mut num_clockwise = 0;
-    let mut num_counter = 0;
-    for sticker in state.0.iter() {
-        match sticker.direction {
-            Clockwise => num_clockwise += 1,
-            Counter => num_counter += 1,
-            _ => ()
-        }
-    }
-    if num_clockwise != 6 || num_counter != 6 {
-        Err("expected 6 clockwise and 6 counter-clockwise arrows".to_owned())
-    } else {
-        Ok(())
+        let five_lines: Vec<&str> = file.lines().take(5).collect();
+        assert!(read_prompts(&mut five_lines.join("\n").as_bytes()).is_err());
+
+        let mut lines: Vec<String> = file.lines().map(|l| l.to_owned()).collect();
+        let moved = lines[0].pop().unwrap();
+        lines[1].insert(0, moved);
+        assert!(lines.join("\n").parse::<State>().is_ok());
+        assert!(read_prompts(&mut lines.join("\n").as_bytes()).is_err());
     }
 }
@@ -2,62 +2,353 @@
 
 extern crate clap;
 extern crate locky_puzzle;
+#[macro_use]
+extern crate serde_json;
+
+/// Print a line of text output, which is left out of JSON output.
+macro_rules! say {
+    ( $args:expr, $( $arg:tt )* ) => {
+        if $args.format == OutputFormat::Text {
+            println!($( $arg )*);
+        }
+    }
+}
 
 mod arguments;
+mod batch;
 mod heuristic;
 mod input;
 
+use std::fmt;
+use std::fmt::{Display, Formatter};
 use std::process::exit;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
 
-use locky_puzzle::{MultiStep, solve};
+use locky_puzzle::{Algo, Anytime, CostModel, Heuristic, MultiStep, MultiStepError, State,
+    TextStyle, TwoPhase, algo_to_json, error_to_json, optimize, render_net, solve, solve_all,
+    state_to_json};
+use serde_json::{Map, Value};
 
-use arguments::{Args, parse_args};
-use heuristic::make_heuristic;
+use arguments::{Args, ArgsError, OutputFormat, parse_args};
+use batch::run_batch;
+use heuristic::{CountingHeuristic, make_heuristic};
 use input::read_state;
 
+/// The fields of the JSON output, which are filled in as the solver runs.
+type Report = Map<String, Value>;
+
+/// A reason that the solver failed.
+enum Failure {
+    Usage(String),
+    Message(String),
+    Solve(MultiStepError)
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &Failure::Usage(ref message) | &Failure::Message(ref message) => {
+                write!(f, "{}", message)
+            },
+            &Failure::Solve(ref e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Failure {
+        Failure::Message(message)
+    }
+}
+
 fn main() {
-    match parse_args() {
+    let (format, result) = match parse_args() {
         Ok(args) => {
-            if let Err(e) = main_with_args(args) {
-                eprintln!("{}", e);
-                exit(1);
-            }
+            let mut report = Report::new();
+            let result = main_with_args(&args, &mut report);
+            (args.format, result.map(|_| report))
+        },
+        Err(ArgsError{format, message}) => (format, Err(Failure::Usage(message)))
+    };
+    match (format, result) {
+        (OutputFormat::Text, Ok(_)) => (),
+        (OutputFormat::Json, Ok(report)) => println!("{}", Value::Object(report)),
+        (OutputFormat::Text, Err(e)) => {
+            eprintln!("{}", describe_failure(&e));
+            exit(1);
         },
-        Err(e) => {
-            eprintln!("{}", e);
+        (OutputFormat::Json, Err(e)) => {
+            println!("{}", json!({"error": failure_to_json(&e)}));
             exit(1);
         }
     }
 }
 
-fn main_with_args(args: Args) -> Result<(), String> {
-    if args.multi_step {
-        return run_multistep(args)
+fn main_with_args(args: &Args, report: &mut Report) -> Result<(), Failure> {
+    if let Some(ref path) = args.batch {
+        return run_batch(args, path, report)
+    } else if args.multi_step {
+        return run_multistep(args, report)
+    } else if args.two_phase {
+        return run_two_phase(args, report)
+    } else if args.anytime {
+        return run_anytime(args, report)
     }
-    let heuristic_future = make_heuristic(&args.heuristic);
-    let state = read_state(&args)?;
-    println!("Waiting for heuristic...");
+    report.insert("mode".to_owned(), json!("optimal"));
+    let start_time = Instant::now();
+    let heuristic_future = make_heuristic(&args.heuristic, args.moves, args.metric);
+    let state = read_state(args)?;
+    print_input_net(args, &state);
+    report.insert("input".to_owned(), state_to_json(&state));
+    say!(args, "Waiting for heuristic...");
     let heuristic = heuristic_future.recv().unwrap();
+    report.insert("heuristic_ms".to_owned(), json!(millis(start_time)));
+
+    let search_time = Instant::now();
+    let counting = CountingHeuristic::new(&heuristic);
+    let mut depths = Vec::new();
     for depth in 0..255 {
-        println!("Trying depth {}...", depth);
-        if let Some(solution) = solve(&state, &heuristic, depth) {
-            println!("Found solution: {}", solution);
+        say!(args, "Trying depth {}...", depth);
+        let depth_time = Instant::now();
+        let result = solve(&state, &counting, args.moves, args.metric, depth);
+        depths.push(json!({
+            "depth": depth,
+            "nodes": counting.take_nodes(),
+            "ms": millis(depth_time)
+        }));
+        if let Some(solution) = result {
+            say!(args, "Found solution: {}", solution);
+            let moves: Vec<(Option<String>, Algo)> = solution.0.iter()
+                .map(|m| (None, Algo(vec![*m])))
+                .collect();
+            print_steps(args, &state, &moves);
+            let length = solution.cost(args.metric);
+            say!(args, "Solution length: {} {}", length, args.metric);
+            report.insert("solution".to_owned(), algo_to_json(&solution));
+            report.insert("length".to_owned(), json!(length));
+            report.insert("metric".to_owned(), json!(args.metric.to_string()));
+            report.insert("depths".to_owned(), json!(depths));
+            report.insert("search_ms".to_owned(), json!(millis(search_time)));
+            if args.ranked > 0 {
+                print_ranked(args, &state, &heuristic, depth.saturating_add(args.slack), report);
+            }
             return Ok(());
         }
     }
+    Err(Failure::Message("no solution within 254 moves".to_owned()))
+}
+
+fn print_ranked<H: Heuristic>(args: &Args, state: &State, heuristic: &H, depth: u8,
+                              report: &mut Report) {
+    say!(args, "Ranking solutions up to length {}...", depth);
+    let model = CostModel::default();
+    let solutions = solve_all(state, heuristic, args.moves, args.metric, depth);
+    let ranked: Vec<Algo> = model.rank(solutions).into_iter().take(args.ranked).collect();
+    let mut ranked_json = Vec::new();
+    for (i, solution) in ranked.iter().enumerate() {
+        let cost = model.algo_cost(solution);
+        say!(args, "{}. {} (cost {:.1})", i + 1, solution, cost);
+        let mut entry = algo_to_json(solution);
+        entry["cost"] = json!(cost);
+        ranked_json.push(entry);
+    }
+    report.insert("ranked".to_owned(), json!(ranked_json));
+}
+
+fn run_multistep(args: &Args, report: &mut Report) -> Result<(), Failure> {
+    report.insert("mode".to_owned(), json!("multi-step"));
+    report.insert("pipeline".to_owned(), json!(args.pipeline));
+    say!(args, "Generating solver...");
+    let start_time = Instant::now();
+    let multi = match args.pipeline.as_str() {
+        "default" => MultiStep::generate_default(),
+        "corners-first" => MultiStep::generate_corners_first(),
+        name => return Err(Failure::Message(format!("unknown pipeline: {}", name)))
+    };
+    report.insert("generate_ms".to_owned(), json!(millis(start_time)));
+    say!(args, "Computing solution...");
+    let state = read_state(args)?;
+    print_input_net(args, &state);
+    report.insert("input".to_owned(), state_to_json(&state));
+    let solve_time = Instant::now();
+    let result = if args.beam_width > 1 {
+        let time_limit = Duration::from_secs(args.time_limit);
+        multi.solve_beam_named(&state, args.beam_width, time_limit, &AtomicBool::new(false))
+    } else {
+        multi.solve_named(&state)
+    };
+    report.insert("solve_ms".to_owned(), json!(millis(solve_time)));
+    let (solution, parts) = result.map_err(Failure::Solve)?;
+    print_named_parts(args, &state, &solution, parts, report);
+    Ok(())
+}
+
+fn run_two_phase(args: &Args, report: &mut Report) -> Result<(), Failure> {
+    report.insert("mode".to_owned(), json!("two-phase"));
+    say!(args, "Generating solver...");
+    let start_time = Instant::now();
+    let two_phase = TwoPhase::generate_default();
+    report.insert("generate_ms".to_owned(), json!(millis(start_time)));
+    say!(args, "Computing solution...");
+    let state = read_state(args)?;
+    print_input_net(args, &state);
+    report.insert("input".to_owned(), state_to_json(&state));
+    let solve_time = Instant::now();
+    let result = two_phase.solve_named(&state, Duration::from_secs(args.time_limit));
+    report.insert("solve_ms".to_owned(), json!(millis(solve_time)));
+    let (solution, parts) = result.map_err(Failure::Solve)?;
+    print_named_parts(args, &state, &solution, parts, report);
+    Ok(())
+}
+
+fn run_anytime(args: &Args, report: &mut Report) -> Result<(), Failure> {
+    report.insert("mode".to_owned(), json!("anytime"));
+    say!(args, "Generating solver...");
+    let start_time = Instant::now();
+    let anytime = Anytime::generate_default();
+    report.insert("generate_ms".to_owned(), json!(millis(start_time)));
+    say!(args, "Computing solutions...");
+    let state = read_state(args)?;
+    print_input_net(args, &state);
+    report.insert("input".to_owned(), state_to_json(&state));
+    let cancel = Arc::new(AtomicBool::new(false));
+    let timer_cancel = cancel.clone();
+    let time_limit = Duration::from_secs(args.time_limit);
+    spawn(move || {
+        sleep(time_limit);
+        timer_cancel.store(true, Ordering::SeqCst);
+    });
+    let solve_time = Instant::now();
+    let mut solutions = Vec::new();
+    anytime.solve(&state, &cancel, |solution| {
+        say!(args, "Solution ({} moves): {}", solution.0.len(), solution);
+        let mut entry = algo_to_json(solution);
+        entry["ms"] = json!(millis(solve_time));
+        solutions.push(entry);
+    });
+    if let Some(best) = solutions.last() {
+        report.insert("solution".to_owned(), best.clone());
+    }
+    report.insert("solutions".to_owned(), json!(solutions));
     Ok(())
 }
 
-fn run_multistep(args: Args) -> Result<(), String> {
-    println!("Generating solver...");
-    let multi = MultiStep::generate_default();
-    println!("Computing solution...");
-    let (solution, parts) = multi.solve(&read_state(&args)?).map_err(|e| format!("{}", e))?;
+fn describe_failure(e: &Failure) -> String {
+    match e {
+        &Failure::Usage(ref message) | &Failure::Message(ref message) => message.clone(),
+        &Failure::Solve(ref e) => {
+            format!("{}\nPartial solution: {}\nState: {}", e, e.partial(), e.state())
+        }
+    }
+}
+
+fn failure_to_json(e: &Failure) -> Value {
+    match e {
+        &Failure::Usage(ref message) => json!({"kind": "usage", "message": message}),
+        &Failure::Message(ref message) => json!({"kind": "error", "message": message}),
+        &Failure::Solve(ref e) => error_to_json(e)
+    }
+}
+
+/// Get the number of milliseconds since a point in time.
+fn millis(start: Instant) -> u64 {
+    let elapsed = start.elapsed();
+    elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64
+}
+
+/// Get the style of the nets to draw, if any.
+fn net_style(args: &Args) -> Option<TextStyle> {
+    match args.net {
+        None if args.verbose_steps => Some(TextStyle::Ascii),
+        style => style
+    }
+}
+
+fn print_input_net(args: &Args, state: &State) {
+    if let Some(style) = net_style(args) {
+        println!("Input state:");
+        if args.verbose_steps {
+            println!("Locked faces: {}", locked_faces(state));
+        }
+        print!("{}", render_net(state, style));
+    }
+}
+
+/// Print the state after each step of a solution, where steps may be named
+/// after the goal they solve.
+fn print_steps(args: &Args, state: &State, steps: &[(Option<String>, Algo)]) {
+    let style = match net_style(args) {
+        Some(style) => style,
+        None => return
+    };
+    let mut state = state.clone();
+    for (i, &(ref goal, ref step)) in steps.iter().enumerate() {
+        step.apply(&mut state);
+        if args.verbose_steps {
+            println!("Step {}: {}", i + 1, step);
+            if let &Some(ref goal) = goal {
+                println!("Solved: {}", goal);
+            }
+            println!("Locked faces: {}", locked_faces(&state));
+        } else {
+            println!("After {}:", step);
+        }
+        print!("{}", render_net(&state, style));
+    }
+}
+
+/// List the locked faces of a state, e.g. "U, F".
+fn locked_faces(state: &State) -> String {
+    let faces: Vec<String> = state.locked_faces().iter().map(|f| f.to_string()).collect();
+    if faces.is_empty() {
+        "none".to_owned()
+    } else {
+        faces.join(", ")
+    }
+}
+
+fn print_named_parts(args: &Args, state: &State, solution: &Algo, parts: Vec<(String, Algo)>,
+                     report: &mut Report) {
+    let steps: Vec<(Option<String>, Algo)> = parts.into_iter()
+        .map(|(name, part)| (Some(name), part))
+        .collect();
+    let parts: Vec<Algo> = steps.iter().map(|&(_, ref part)| part.clone()).collect();
+    if args.format == OutputFormat::Text {
+        print_parts(solution, &parts);
+    }
+    print_steps(args, state, &steps);
+
+    let mut parts_json = Vec::new();
+    let mut part_state = state.clone();
+    for &(ref goal, ref part) in &steps {
+        part.apply(&mut part_state);
+        let mut entry = algo_to_json(part);
+        entry["goal"] = json!(goal);
+        entry["state"] = state_to_json(&part_state);
+        parts_json.push(entry);
+    }
+    report.insert("solution".to_owned(), algo_to_json(solution));
+    report.insert("parts".to_owned(), json!(parts_json));
+    print_optimized(args, state, solution, report);
+}
+
+fn print_parts(solution: &Algo, parts: &[Algo]) {
     println!("Solution: {}", solution);
     print!("Parts:");
-    for part in &parts {
+    for part in parts {
         print!(" [  {}  ]", part)
     }
     println!("");
-    Ok(())
+}
+
+fn print_optimized(args: &Args, state: &State, solution: &Algo, report: &mut Report) {
+    if args.optimize > 0 {
+        let optimized = optimize(state, solution, args.optimize);
+        say!(args, "Optimized solution: {}", optimized);
+        say!(args, "Saved {} moves", solution.0.len() - optimized.0.len());
+        report.insert("optimized".to_owned(), algo_to_json(&optimized));
+    }
 }
@@ -0,0 +1,120 @@
+//! A tool for checking solutions to the locky puzzle.
+
+extern crate clap;
+extern crate locky_puzzle;
+#[macro_use]
+extern crate serde_json;
+
+use std::process::exit;
+
+use clap::{App, Arg, ArgMatches};
+use locky_puzzle::{Algo, Goal, State, VerifyError, algo_to_json, state_to_json, verify,
+    verify_error_to_json};
+use serde_json::Value;
+
+fn main() {
+    let matches = App::new("locky-verify")
+        .arg(Arg::with_name("scramble")
+            .long("scramble")
+            .value_name("ALGO")
+            .takes_value(true)
+            .conflicts_with("state")
+            .help("Start from the state after a scramble"))
+        .arg(Arg::with_name("state")
+            .long("state")
+            .value_name("STATE")
+            .takes_value(true)
+            .help("Start from a state, in facelet or display format"))
+        .arg(Arg::with_name("solution")
+            .long("solution")
+            .value_name("ALGO")
+            .takes_value(true)
+            .required(true)
+            .help("Set the solution to check"))
+        .arg(Arg::with_name("goal")
+            .long("goal")
+            .value_name("GOAL")
+            .takes_value(true)
+            .help("Check for a projection such as CornerProj instead of solved (default: solved)"))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .value_name("FORMAT")
+            .takes_value(true)
+            .help("Print the result as text or as a json object (default: text)"))
+        .get_matches();
+    let json = match matches.value_of("format").unwrap_or("text") {
+        "text" => false,
+        "json" => true,
+        f => {
+            eprintln!("bad format argument: {}", f);
+            exit(1);
+        }
+    };
+    let (start, solution, goal) = match parse_inputs(&matches) {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            if json {
+                println!("{}", json!({"error": {"kind": "usage", "message": e}}));
+            } else {
+                eprintln!("{}", e);
+            }
+            exit(1);
+        }
+    };
+    let result = verify(&start, &solution, &goal);
+    if json {
+        println!("{}", result_to_json(&solution, &goal, &result));
+    } else {
+        match result {
+            Ok(ref state) => {
+                println!("Valid: {} moves reach {}", solution.0.len(), goal);
+                println!("Final state: {}", state);
+            },
+            Err(ref e) => {
+                println!("Invalid: {}", e);
+                if let &VerifyError::IllegalMove{index, ..} = e {
+                    println!("Moves before: {}", Algo(solution.0[..index].to_vec()));
+                }
+                println!("State: {}", e.state());
+                let locked: Vec<String> = e.state().locked_faces().iter()
+                    .map(|f| f.to_string())
+                    .collect();
+                println!("Locked faces: {}", locked.join(" "));
+            }
+        }
+    }
+    if result.is_err() {
+        exit(1);
+    }
+}
+
+fn parse_inputs(matches: &ArgMatches) -> Result<(State, Algo, Goal), String> {
+    let start = if let Some(scramble) = matches.value_of("scramble") {
+        let algo: Algo = scramble.parse().map_err(|e| format!("parse scramble: {}", e))?;
+        algo.legal_state().map_err(|e| format!("illegal scramble: {}", e))?
+    } else if let Some(state) = matches.value_of("state") {
+        state.parse().map_err(|e| format!("parse state: {}", e))?
+    } else {
+        return Err("a starting state is required (use --scramble or --state)".to_owned());
+    };
+    let solution = matches.value_of("solution").unwrap().parse()
+        .map_err(|e| format!("parse solution: {}", e))?;
+    let goal = match matches.value_of("goal") {
+        Some(goal) => goal.parse().map_err(|e| format!("bad goal argument: {}", e))?,
+        None => Goal::solved()
+    };
+    Ok((start, solution, goal))
+}
+
+fn result_to_json(solution: &Algo, goal: &Goal, result: &Result<State, VerifyError>) -> Value {
+    let mut res = json!({
+        "solution": algo_to_json(solution),
+        "goal": goal.to_string(),
+        "valid": result.is_ok()
+    });
+    match result {
+        &Ok(ref state) => res["state"] = state_to_json(state),
+        &Err(ref e) => res["error"] = verify_error_to_json(e)
+    }
+    res
+}
@@ -0,0 +1,199 @@
+//! A two-phase solver which trades off the lengths of its two phases.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use super::heuristic::{CancelHeuristic, Heuristic, MaxHeuristic};
+use super::moves::{Algo, Metric, MoveSet};
+use super::multi_step::{MultiStep, MultiStepError, corner_steps, generate_table, strip_names};
+use super::proj::{ArrowAxisProj, CoFbProj, CoRlProj, CoUdProj, CornerProj, LockProj, PairProj,
+    Proj};
+use super::solve::proj_solve_exact;
+use super::state::State;
+
+/// The goal of phase one of the default two-phase solver, where the arrows
+/// are on their own axes and the corners are oriented.
+pub type Phase1Proj = PairProj<PairProj<ArrowAxisProj, CoFbProj>, PairProj<CoRlProj, CoUdProj>>;
+
+/// A two-phase solver.
+///
+/// Phase one brings the puzzle into the set of states that are solved under
+/// the projection P, and phase two solves the rest of the puzzle.
+/// Unlike MultiStep, phase one is not committed to its shortest solution.
+/// Instead, the solver keeps trying longer phase one solutions, since these
+/// may leave the puzzle in a state with a much shorter phase two.
+pub struct TwoPhase<P: Proj> {
+    phase1: Arc<Heuristic>,
+    max_phase1_depth: u8,
+    phase2: MultiStep,
+    phantom: PhantomData<P>
+}
+
+impl<P: Proj> TwoPhase<P> {
+    /// Create a two-phase solver.
+    ///
+    /// The phase one heuristic must be a lower bound for solving P.
+    /// The phase two pipeline is expected to solve states that are solved
+    /// under P.
+    pub fn new(phase1: Arc<Heuristic>, max_phase1_depth: u8, phase2: MultiStep) -> TwoPhase<P> {
+        TwoPhase{
+            phase1: phase1,
+            max_phase1_depth: max_phase1_depth,
+            phase2: phase2,
+            phantom: PhantomData
+        }
+    }
+
+    /// Find a solution for the state.
+    ///
+    /// Phase one solutions are tried in order of increasing length, and the
+    /// shortest total solution is kept.
+    /// The search stops when no phase one solution could improve on the best
+    /// solution, or when the time limit has passed.
+    /// If no solution was found by then, the solve fails with
+    /// MultiStepError::Cancelled.
+    ///
+    /// Returns both the complete solution, and a decomposition of the
+    /// solution into phase one followed by the parts of phase two.
+    pub fn solve(
+        &self,
+        s: &State,
+        time_limit: Duration
+    ) -> Result<(Algo, Vec<Algo>), MultiStepError> {
+        self.solve_named(s, time_limit).map(strip_names)
+    }
+
+    /// Find a solution for the state like solve(), but name each part of the
+    /// solution, where phase one is named "phase 1" and the parts of phase
+    /// two are named after their steps.
+    pub fn solve_named(
+        &self,
+        s: &State,
+        time_limit: Duration
+    ) -> Result<(Algo, Vec<(String, Algo)>), MultiStepError> {
+        let never = AtomicBool::new(false);
+        let deadline = Instant::now() + time_limit;
+        let heuristic = CancelHeuristic::with_deadline(&*self.phase1, &never, Some(deadline));
+        let mut best: Option<(Algo, Vec<(String, Algo)>)> = None;
+        let mut phase2_error = None;
+        let min_depth = self.phase1.lower_bound(s, LockProj::project(s));
+        for depth in min_depth..=self.max_phase1_depth {
+            if heuristic.is_cancelled() {
+                break;
+            } else if let Some((ref solution, _)) = best {
+                if solution.0.len() <= depth as usize {
+                    break;
+                }
+            }
+            proj_solve_exact::<P, _, _>(s, &heuristic, MoveSet::all(), Metric::FaceTurn, depth,
+                                        |moves| {
+                let phase1 = Algo(moves.to_vec());
+                let mut state = s.clone();
+                phase1.apply(&mut state);
+                match self.phase2.solve_cancel(&state, &never, Some(deadline)) {
+                    Ok((phase2, mut parts)) => {
+                        let length = phase1.0.len() + phase2.0.len();
+                        if best.as_ref().map(|&(ref b, _)| length < b.0.len()).unwrap_or(true) {
+                            parts.insert(0, ("phase 1".to_owned(), phase1.clone()));
+                            best = Some((concat(&phase1, &phase2), parts));
+                        }
+                    },
+                    Err(MultiStepError::Cancelled{..}) => (),
+                    Err(e) => phase2_error = Some(phase2_failure(&phase1, e))
+                }
+                heuristic.is_cancelled()
+            });
+        }
+        best.ok_or_else(|| if heuristic.is_cancelled() {
+            MultiStepError::Cancelled{partial: Algo(Vec::new()), state: Box::new(s.clone())}
+        } else {
+            phase2_error.unwrap_or_else(|| MultiStepError::StepFailed{
+                index: 0,
+                name: "phase 1".to_owned(),
+                depth: self.max_phase1_depth,
+                partial: Algo(Vec::new()),
+                state: Box::new(s.clone())
+            })
+        })
+    }
+}
+
+/// Turn a phase two error into an error for the whole two-phase solve, where
+/// phase one counts as the first step.
+fn phase2_failure(phase1: &Algo, e: MultiStepError) -> MultiStepError {
+    use MultiStepError::*;
+    match e {
+        StepFailed{index, name, depth, partial, state} => StepFailed{
+            index: index + 1,
+            name: name,
+            depth: depth,
+            partial: concat(phase1, &partial),
+            state: state
+        },
+        Unsolved{partial, state} => Unsolved{partial: concat(phase1, &partial), state: state},
+        Cancelled{partial, state} => Cancelled{partial: concat(phase1, &partial), state: state}
+    }
+}
+
+fn concat(a1: &Algo, a2: &Algo) -> Algo {
+    Algo(a1.0.iter().chain(&a2.0).cloned().collect())
+}
+
+impl TwoPhase<Phase1Proj> {
+    /// Generate a two-phase solver with reasonable default settings.
+    ///
+    /// Phase one unlocks the faces, orients the edges and orients the
+    /// corners (`ArrowAxisProj` and the `Co*Proj` projections).
+    /// Phase two solves the corners and then the rest of the puzzle with
+    /// every move, like the last steps of the default MultiStep pipeline.
+    /// Every move can lock faces, so there is no smaller set of moves that
+    /// keeps the puzzle in the states that phase one reaches.
+    pub fn generate_default() -> TwoPhase<Phase1Proj> {
+        let arrow_rx = generate_table::<ArrowAxisProj>(7);
+        let co_fb_rx = generate_table::<CoFbProj>(7);
+        let co_rl_rx = generate_table::<CoRlProj>(7);
+        let co_ud_rx = generate_table::<CoUdProj>(7);
+        let corner_rx = generate_table::<CornerProj>(7);
+
+        let arrow = arrow_rx.recv().unwrap();
+        let co_fb = co_fb_rx.recv().unwrap();
+        let co_rl = co_rl_rx.recv().unwrap();
+        let co_ud = co_ud_rx.recv().unwrap();
+        let phase1: Arc<Heuristic> = Arc::new(MaxHeuristic(vec![arrow.clone(), co_fb.clone(),
+            co_rl.clone(), co_ud.clone()]));
+        let mut phase2 = corner_steps(arrow, co_fb, co_rl, co_ud, corner_rx.recv().unwrap());
+        // The first of the corner steps is phase one.
+        phase2.remove(0);
+        TwoPhase::new(phase1, 254, MultiStep::new(phase2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heuristic::NopHeuristic;
+    use proj::LockProj;
+    use step::SolveStep;
+
+    /// Test that the two-phase solver finds a short solution.
+    #[test]
+    fn short_scramble() {
+        let nop: Arc<Heuristic> = Arc::new(NopHeuristic());
+        let phase2 = MultiStep::new(vec![Box::new(SolveStep::new(nop.clone(), 5))]);
+        let two_phase = TwoPhase::<LockProj>::new(nop, 5, phase2);
+        let algo: Algo = "B D2 B' U2 L2".parse().unwrap();
+        let (solution, parts) = two_phase.solve(&algo.state(), Duration::from_secs(1000)).unwrap();
+        assert_eq!(solution, "L2 U2 B D2 B'".parse().unwrap());
+        assert_eq!(parts.len(), 2);
+
+        match two_phase.solve(&algo.state(), Duration::from_secs(0)) {
+            Err(MultiStepError::Cancelled{ref partial, ref state}) => {
+                assert_eq!(partial, &Algo(Vec::new()));
+                assert_eq!(**state, algo.state());
+            },
+            res => panic!("unexpected result: {:?}", res)
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Checking candidate solutions from other solvers or from humans.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::error::Error;
+use std::str::FromStr;
+
+use super::moves::{Algo, Move};
+use super::proj;
+use super::proj::Proj;
+use super::state::State;
+
+/// A single requirement of a goal: "solved" or a projection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum GoalPart {
+    Solved,
+    LockProj,
+    CornerProj,
+    ArrowAxisProj,
+    CoProj,
+    CoUdProj,
+    CoFbProj,
+    CoRlProj,
+    CornerUdProj,
+    CornerFbProj,
+    CornerRlProj
+}
+
+/// Every goal part, in the order they are listed in error messages.
+const GOAL_PARTS: [GoalPart; 11] = [GoalPart::Solved, GoalPart::LockProj, GoalPart::CornerProj,
+    GoalPart::ArrowAxisProj, GoalPart::CoProj, GoalPart::CoUdProj, GoalPart::CoFbProj,
+    GoalPart::CoRlProj, GoalPart::CornerUdProj, GoalPart::CornerFbProj, GoalPart::CornerRlProj];
+
+impl GoalPart {
+    fn name(&self) -> &'static str {
+        use self::GoalPart::*;
+        match *self {
+            Solved => "solved",
+            LockProj => "LockProj",
+            CornerProj => "CornerProj",
+            ArrowAxisProj => "ArrowAxisProj",
+            CoProj => "CoProj",
+            CoUdProj => "CoUdProj",
+            CoFbProj => "CoFbProj",
+            CoRlProj => "CoRlProj",
+            CornerUdProj => "CornerUdProj",
+            CornerFbProj => "CornerFbProj",
+            CornerRlProj => "CornerRlProj"
+        }
+    }
+
+    fn is_reached(&self, s: &State) -> bool {
+        use self::GoalPart::*;
+        match *self {
+            Solved => s.is_solved(),
+            LockProj => proj_solved::<proj::LockProj>(s),
+            CornerProj => proj_solved::<proj::CornerProj>(s),
+            ArrowAxisProj => proj_solved::<proj::ArrowAxisProj>(s),
+            CoProj => CoUdProj.is_reached(s) && CoFbProj.is_reached(s) && CoRlProj.is_reached(s),
+            CoUdProj => proj_solved::<proj::CoUdProj>(s),
+            CoFbProj => proj_solved::<proj::CoFbProj>(s),
+            CoRlProj => proj_solved::<proj::CoRlProj>(s),
+            CornerUdProj => proj_solved::<proj::CornerUdProj>(s),
+            CornerFbProj => proj_solved::<proj::CornerFbProj>(s),
+            CornerRlProj => proj_solved::<proj::CornerRlProj>(s)
+        }
+    }
+}
+
+/// The state that a solution is expected to reach.
+///
+/// A goal is either "solved" or a projection, such as "CornerProj". Several
+/// goals may be combined with '+', as in the step names of MultiStep (e.g.
+/// "ArrowAxisProj+CornerProj"), in which case all of them must be reached.
+/// "CoProj" stands for CoUdProj, CoFbProj and CoRlProj together.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Goal(Vec<GoalPart>);
+
+impl Goal {
+    /// Get the goal of solving the puzzle completely.
+    pub fn solved() -> Goal {
+        Goal(vec![GoalPart::Solved])
+    }
+
+    /// Check if a state reaches the goal.
+    pub fn is_reached(&self, s: &State) -> bool {
+        self.0.iter().all(|part| part.is_reached(s))
+    }
+}
+
+impl Default for Goal {
+    fn default() -> Goal {
+        Goal::solved()
+    }
+}
+
+impl FromStr for Goal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Goal, String> {
+        let mut parts = Vec::new();
+        for name in s.split('+').map(|n| n.trim()) {
+            match GOAL_PARTS.iter().find(|part| part.name() == name) {
+                Some(part) => parts.push(*part),
+                None => {
+                    let names: Vec<&str> = GOAL_PARTS.iter().map(|part| part.name()).collect();
+                    return Err(format!("unknown goal: {} (expected one of {})", name,
+                        names.join(", ")));
+                }
+            }
+        }
+        Ok(Goal(parts))
+    }
+}
+
+impl Display for Goal {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let names: Vec<&str> = self.0.iter().map(|part| part.name()).collect();
+        write!(f, "{}", names.join("+"))
+    }
+}
+
+/// Check if a state is solved under a projection.
+fn proj_solved<P: Proj>(s: &State) -> bool {
+    P::project(s) == P::project(&State::default())
+}
+
+/// Check that a solution only turns unlocked faces and reaches the goal.
+///
+/// Returns the state that the solution leaves the puzzle in.
+pub fn verify(start: &State, solution: &Algo, goal: &Goal) -> Result<State, VerifyError> {
+    let mut state = start.clone();
+    for (i, m) in solution.0.iter().enumerate() {
+        if state.is_locked(m.face) {
+            return Err(VerifyError::IllegalMove{index: i, m: *m, state: state});
+        }
+        m.apply(&mut state);
+    }
+    if !goal.is_reached(&state) {
+        return Err(VerifyError::Unsolved{goal: goal.clone(), state: state});
+    }
+    Ok(state)
+}
+
+/// The reason that a solution is invalid.
+#[derive(Clone, Debug)]
+pub enum VerifyError {
+    /// A move turned a locked face.
+    ///
+    /// The index counts from zero, and the state is the one before the move.
+    IllegalMove{index: usize, m: Move, state: State},
+
+    /// Every move was legal, but the goal was not reached.
+    Unsolved{goal: Goal, state: State}
+}
+
+impl VerifyError {
+    /// Get the state of the puzzle when the check failed.
+    pub fn state(&self) -> &State {
+        use VerifyError::*;
+        match *self {
+            IllegalMove{ref state, ..} => state,
+            Unsolved{ref state, ..} => state
+        }
+    }
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        use VerifyError::*;
+        match *self {
+            IllegalMove{index, m, ..} => {
+                write!(f, "move {} ({}) turns the locked {} face", index + 1, m, m.face)
+            },
+            Unsolved{ref goal, ..} => write!(f, "{}: {}", self.description(), goal)
+        }
+    }
+}
+
+impl Error for VerifyError {
+    fn description(&self) -> &str {
+        use VerifyError::*;
+        match self {
+            &IllegalMove{..} => "illegal move",
+            &Unsolved{..} => "solution does not reach the goal"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test verifying legal, illegal and incomplete solutions.
+    #[test]
+    fn verify_solutions() {
+        let start = "F U".parse::<Algo>().unwrap().state();
+        let solution: Algo = "U' F'".parse().unwrap();
+        assert_eq!(verify(&start, &solution, &Goal::solved()).unwrap(), State::default());
+
+        // After F, the R face is locked.
+        let start = "F".parse::<Algo>().unwrap().state();
+        match verify(&start, &"R F'".parse().unwrap(), &Goal::solved()) {
+            Err(VerifyError::IllegalMove{index, ..}) => assert_eq!(index, 0),
+            _ => panic!("expected an illegal move")
+        }
+
+        match verify(&start, &Algo(Vec::new()), &Goal::solved()) {
+            Err(VerifyError::Unsolved{state, ..}) => assert_eq!(state, start),
+            _ => panic!("expected an unsolved state")
+        }
+
+        let goal: Goal = "ArrowAxisProj+CoProj".parse().unwrap();
+        assert_eq!(goal.to_string(), "ArrowAxisProj+CoProj");
+        assert!(verify(&State::default(), &Algo(Vec::new()), &goal).is_ok());
+        assert!(verify(&start, &Algo(Vec::new()), &goal).is_err());
+        assert!("CornerProj+Nope".parse::<Goal>().is_err());
+    }
+}